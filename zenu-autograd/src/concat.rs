@@ -1,8 +1,238 @@
-use zenu_matrix::{concat::concat as c, num::Num};
+use std::{cell::RefCell, rc::Rc};
 
-use crate::Variable;
+use zenu_matrix::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, MatrixSliceMutDyn},
+    num::Num,
+    operation::copy_from::CopyFrom,
+    slice::slice_dim::SliceDim,
+};
 
-pub fn concat<T: Num>(vars: &[Variable<T>]) -> Variable<T> {
-    let matrix = vars.iter().map(|v| v.get_data()).collect::<Vec<_>>();
-    Variable::from(c(&matrix))
+use crate::{
+    creator::zeros::zeros,
+    functions::{
+        reshape::reshape,
+        slice::{axis_slice, narrow},
+    },
+    Function, Variable, VariableWeak,
+};
+
+struct Concat<T: Num> {
+    inputs: Vec<Variable<T>>,
+    axis: usize,
+    output: VariableWeak<T>,
+}
+
+impl<T: Num> Concat<T> {
+    /// Start and length of every input along `axis`.
+    fn ranges(&self) -> Vec<(usize, usize)> {
+        let mut start = 0;
+        self.inputs
+            .iter()
+            .map(|input| {
+                let len = input.get_data().shape()[self.axis];
+                start += len;
+                (start - len, len)
+            })
+            .collect()
+    }
+}
+
+impl<T: Num> Function<T> for Concat<T> {
+    fn forward(&self) {
+        let output = self.output.upgrade().unwrap();
+        let mut output = output.get_data_mut();
+        let ndim = output.shape().len();
+        for (input, (start, len)) in self.inputs.iter().zip(self.ranges()) {
+            let index = axis_slice(ndim, self.axis, SliceDim::from(start..start + len));
+            output.slice_mut_dyn(index).copy_from(&input.get_data());
+        }
+    }
+
+    fn backward(&self) {
+        let output_grad = self.output.upgrade().unwrap().get_grad().unwrap();
+        for (input, (start, len)) in self.inputs.iter().zip(self.ranges()) {
+            input.set_grad(narrow(output_grad.clone(), self.axis, start, len));
+        }
+    }
+
+    fn get_inputs(&self) -> Vec<Variable<T>> {
+        self.inputs.clone()
+    }
+}
+
+/// Joins `vars` along an existing `axis`. All shapes must agree except along `axis`; the
+/// gradient is split back into the pieces that came from each input.
+pub fn concat<T: Num>(vars: &[Variable<T>], axis: usize) -> Variable<T> {
+    assert!(!vars.is_empty(), "concat needs at least one variable");
+    let first_shape = vars[0].get_data().shape();
+    assert!(axis < first_shape.len(), "axis out of range");
+
+    let mut len = 0;
+    for var in vars {
+        let shape = var.get_data().shape();
+        assert_eq!(
+            shape.len(),
+            first_shape.len(),
+            "all variables must have the same number of dimensions"
+        );
+        for i in 0..shape.len() {
+            if i != axis && shape[i] != first_shape[i] {
+                panic!("all shapes must match except along the concatenated axis");
+            }
+        }
+        len += shape[axis];
+    }
+
+    let mut output_shape = first_shape.slice().to_vec();
+    output_shape[axis] = len;
+    let output = zeros(DimDyn::from(output_shape.as_slice()));
+    let concat = Concat {
+        inputs: vars.to_vec(),
+        axis,
+        output: output.clone().downgrade(),
+    };
+    concat.forward();
+    output.set_creator(Rc::new(RefCell::new(Box::new(concat))));
+    output
+}
+
+/// Joins `vars`, which must all have the same shape, along a new `axis`.
+pub fn stack<T: Num>(vars: &[Variable<T>], axis: usize) -> Variable<T> {
+    assert!(!vars.is_empty(), "stack needs at least one variable");
+    let shape = vars[0].get_data().shape();
+    assert!(axis <= shape.len(), "axis out of range");
+    let mut expanded = shape.slice().to_vec();
+    expanded.insert(axis, 1);
+
+    let vars = vars
+        .iter()
+        .map(|var| {
+            assert_eq!(
+                var.get_data().shape(),
+                shape,
+                "all variables must have the same shape"
+            );
+            reshape(var.clone(), &expanded)
+        })
+        .collect::<Vec<_>>();
+    concat(&vars, axis)
+}
+
+/// Splits `x` along `axis` into consecutive pieces of the given `sizes`, which must add up
+/// to the length of that axis. Each piece back-propagates into its own part of `x`.
+pub fn split<T: Num>(x: Variable<T>, axis: usize, sizes: &[usize]) -> Vec<Variable<T>> {
+    let shape = x.get_data().shape();
+    assert!(axis < shape.len(), "axis out of range");
+    assert_eq!(
+        sizes.iter().sum::<usize>(),
+        shape[axis],
+        "split sizes must add up to the length of the axis"
+    );
+    let mut start = 0;
+    sizes
+        .iter()
+        .map(|&len| {
+            let piece = narrow(x.clone(), axis, start, len);
+            start += len;
+            piece
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod concat {
+    use zenu_matrix::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::asum::Asum,
+    };
+
+    use crate::{
+        creator::from_vec::from_vec, functions::slice::index_axis, gradcheck::gradcheck, Variable,
+    };
+
+    use super::{concat, split, stack};
+
+    fn arange(shape: &[usize], offset: f64) -> Variable<f64> {
+        let n = shape.iter().product::<usize>();
+        from_vec((0..n).map(|x| x as f64 + offset).collect(), shape)
+    }
+
+    #[test]
+    fn concat_axis_1() {
+        let a = arange(&[2, 2], 0.);
+        let b = arange(&[2, 3], 10.);
+        let c = concat(&[a.clone(), b.clone()], 1);
+        let ans =
+            OwnedMatrixDyn::from_vec(vec![0., 1., 10., 11., 12., 2., 3., 13., 14., 15.], [2, 5]);
+        assert!((c.get_data().to_view() - ans.to_view()).asum() < 1e-12);
+
+        let weight = from_vec((1..=10).map(f64::from).collect(), [2, 5]);
+        (c * weight).backward();
+        let a_grad = OwnedMatrixDyn::from_vec(vec![1., 2., 6., 7.], [2, 2]);
+        let b_grad = OwnedMatrixDyn::from_vec(vec![3., 4., 5., 8., 9., 10.], [2, 3]);
+        let a_diff = a.get_grad().unwrap().get_data().to_view() - a_grad.to_view();
+        let b_diff = b.get_grad().unwrap().get_data().to_view() - b_grad.to_view();
+        assert!(a_diff.asum() < 1e-12);
+        assert!(b_diff.asum() < 1e-12);
+    }
+
+    #[test]
+    fn stack_new_axis() {
+        let a = arange(&[2, 3], 0.);
+        let b = arange(&[2, 3], 10.);
+        let s = stack(&[a.clone(), b], 1);
+        assert_eq!(s.get_data().shape().slice(), [2, 2, 3]);
+        assert_eq!(s.get_data().index_item([1, 0, 2]), 5.);
+        assert_eq!(s.get_data().index_item([1, 1, 2]), 15.);
+
+        let s0 = stack(&[a.clone(), a], 0);
+        assert_eq!(s0.get_data().shape().slice(), [2, 2, 3]);
+    }
+
+    #[test]
+    fn split_roundtrip() {
+        let x = arange(&[5, 2], 0.);
+        let pieces = split(x.clone(), 0, &[1, 3, 1]);
+        assert_eq!(pieces[1].get_data().shape().slice(), [3, 2]);
+        assert_eq!(pieces[2].get_data().index_item([0, 1]), 9.);
+
+        let joined = concat(&pieces, 0);
+        assert!((joined.get_data().to_view() - x.get_data().to_view()).asum() < 1e-12);
+    }
+
+    #[test]
+    fn gradcheck_concat_stack_split() {
+        let a = from_vec(vec![0.5, -1., 2., 0.3], [2, 2]);
+        let b = from_vec(vec![1.1, -0.7, 0.2, 0.9, -1.3, 0.4], [2, 3]);
+        gradcheck(
+            |v| {
+                let c = concat(&[v[0].clone(), v[1].clone()], 1);
+                let parts = split(c.clone(), 1, &[3, 2]);
+                let s = stack(&[parts[1].clone(), v[0].clone()], 0);
+                let s = index_axis(concat(&[s.clone(), s], 2), 0, 0);
+                concat(
+                    &[
+                        parts[0].clone() * parts[0].clone(),
+                        c.clone() * c,
+                        s.clone() * s,
+                    ],
+                    1,
+                )
+            },
+            &[a, b],
+            1e-6,
+            1e-6,
+            1e-4,
+        )
+        .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "all shapes must match")]
+    fn concat_shape_mismatch() {
+        concat(&[arange(&[2, 2], 0.), arange(&[3, 2], 0.)], 1);
+    }
 }
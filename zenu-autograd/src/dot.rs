@@ -0,0 +1,129 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use zenu_matrix::{dim::DimTrait, matrix::MatrixBase, num::Num};
+
+use crate::Variable;
+
+impl<T: Num> Variable<T> {
+    /// Renders the graph that produced this variable in Graphviz DOT format.
+    ///
+    /// Variables are drawn as ellipses labelled with their name, shape and whether a gradient
+    /// is present (filled when it is). Functions are boxes labelled with
+    /// [`Function::name`](crate::Function::name). Edges point from inputs to functions and from
+    /// functions to the variables they created.
+    ///
+    /// ```
+    /// use zenu_autograd::creator::from_vec::from_vec;
+    ///
+    /// let x = from_vec(vec![1., 2.], [2]);
+    /// x.set_name("x");
+    /// let y = x.clone() * x;
+    /// assert!(y.to_dot().contains("label=\"Multiply\""));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph {\n");
+        let mut var_ids = HashMap::new();
+        let mut func_ids = HashMap::new();
+        let mut visited_vars = HashSet::new();
+        let mut visited_funcs = HashSet::new();
+        let mut stack = vec![self.clone()];
+
+        while let Some(variable) = stack.pop() {
+            let var_ptr = variable.inner.as_ptr() as usize;
+            if !visited_vars.insert(var_ptr) {
+                continue;
+            }
+            let var_id = node_id(&mut var_ids, var_ptr);
+            writeln!(dot, "  v{var_id} [{}];", variable_attrs(&variable)).unwrap();
+
+            let Some(creator) = variable.get_creator() else {
+                continue;
+            };
+            let func_ptr = creator.as_ptr() as *const u8 as usize;
+            let func_id = node_id(&mut func_ids, func_ptr);
+            writeln!(dot, "  f{func_id} -> v{var_id};").unwrap();
+            if !visited_funcs.insert(func_ptr) {
+                continue;
+            }
+
+            let func = creator.borrow();
+            writeln!(
+                dot,
+                "  f{func_id} [label=\"{}\", shape=box];",
+                escape(func.name())
+            )
+            .unwrap();
+            for input in func.get_inputs() {
+                let input_id = node_id(&mut var_ids, input.inner.as_ptr() as usize);
+                writeln!(dot, "  v{input_id} -> f{func_id};").unwrap();
+                stack.push(input);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn node_id(ids: &mut HashMap<usize, usize>, ptr: usize) -> usize {
+    let next = ids.len();
+    *ids.entry(ptr).or_insert(next)
+}
+
+fn variable_attrs<T: Num>(variable: &Variable<T>) -> String {
+    let name = variable.get_name().unwrap_or_default();
+    let shape = variable.get_data().shape();
+    let has_grad = variable.get_grad().is_some();
+    let mut label = String::new();
+    if !name.is_empty() {
+        label.push_str(&escape(&name));
+        label.push_str("\\n");
+    }
+    write!(label, "{:?}", shape.slice()).unwrap();
+    if has_grad {
+        label.push_str("\\ngrad");
+        format!("label=\"{label}\", shape=ellipse, style=filled, fillcolor=lightblue")
+    } else {
+        format!("label=\"{label}\", shape=ellipse")
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod dot {
+    use crate::{creator::from_vec::from_vec, functions::exp::exp};
+
+    #[test]
+    fn shared_input() {
+        let x = from_vec(vec![1., 2., 3.], [3]);
+        x.set_name("x");
+        let y = exp(x.clone()) * x.clone();
+        y.set_name("y");
+        y.backward();
+        let dot = y.to_dot();
+
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.ends_with("}\n"));
+        // x, exp(x), y
+        assert_eq!(dot.matches("shape=ellipse").count(), 3);
+        assert_eq!(dot.matches("shape=box").count(), 2);
+        assert!(dot.contains("label=\"Multiply\""));
+        assert!(dot.contains("label=\"Exp\""));
+        assert!(dot.contains("label=\"x\\n[3]\\ngrad\""));
+        // x -> exp, x -> mul, exp(x) -> mul, and one edge from each function to its output
+        assert_eq!(dot.matches(" -> ").count(), 5);
+    }
+
+    #[test]
+    fn escapes_names() {
+        let x = from_vec(vec![1.], [1]);
+        x.set_name("a \"quoted\" name");
+        let dot = x.to_dot();
+        assert!(dot.contains("label=\"a \\\"quoted\\\" name\\n[1]\""));
+    }
+}
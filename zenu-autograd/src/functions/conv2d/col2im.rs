@@ -1,14 +1,17 @@
 use zenu_matrix::{
     constructor::zeros::Zeros,
     dim::DimDyn,
-    matrix::{MatrixSliceDyn, MatrixSliceMutDyn, ToOwnedMatrix},
+    matrix::{AsMutPtr, AsPtr, MatrixBase, ToViewMutMatrix},
     matrix_impl::{Matrix, OwnedMatrixDyn},
     memory_impl::{OwnedMem, ViewMem},
     num::Num,
-    operation::basic_operations::MatrixAddAssign,
-    slice_dynamic,
 };
 
+use super::parallel::{for_each_chunk, SyncPtr};
+
+/// `[batch, c, kh, kw, oh, ow]`の`col`を画像に足し戻す。`col`は転置されたビューでもよい。
+/// 出力の`(batch, c)`平面ごとにスレッドに分ける。各画素へは従来と同じ`(kh, kw)`の順に
+/// 加算するので、結果は逐次実装とビット単位で一致する。
 pub(crate) fn col2im<T: Num>(
     col: Matrix<ViewMem<T>, DimDyn>,
     img_shape: [usize; 4],
@@ -22,34 +25,56 @@ pub(crate) fn col2im<T: Num>(
     let (ph, pw) = pad;
     let (oh, ow) = ((h + 2 * ph - kh) / sh + 1, (w + 2 * pw - kw) / sw + 1);
 
-    let mut img = OwnedMatrixDyn::zeros([batch_size, c, h + 2 * ph + sh - 1, w + 2 * pw + sw - 1]);
-
-    for j in 0..kh {
-        let j_lim = j + sh * oh;
-        for i in 0..kw {
-            let i_lim = i + sw * ow;
-            let col = col.slice_dyn(slice_dynamic!(.., .., j, i, .., ..));
+    let col_stride = col.stride();
+    let col_stride = [
+        col_stride[0],
+        col_stride[1],
+        col_stride[2],
+        col_stride[3],
+        col_stride[4],
+        col_stride[5],
+    ];
+    let col_ptr = SyncPtr::new(col.as_ptr());
 
-            let mut img_slice = img.slice_mut_dyn(slice_dynamic!(
-                ..,
-                ..,
-                j..j_lim;sh,
-                i..i_lim;sw
-            ));
-            img_slice.add_assign(col);
+    // caching allocatorから確保した行列にそのまま足し込む
+    let mut img: OwnedMatrixDyn<T> = Zeros::zeros(img_shape);
+    let img_buf = unsafe {
+        std::slice::from_raw_parts_mut(img.to_view_mut().as_mut_ptr(), batch_size * c * h * w)
+    };
+    for_each_chunk(img_buf, h * w, |plane, out| {
+        let (b, ci) = (plane / c, plane % c);
+        let base = b * col_stride[0] + ci * col_stride[1];
+        for j in 0..kh {
+            for i in 0..kw {
+                let kernel_base = base + j * col_stride[2] + i * col_stride[3];
+                for y in 0..oh {
+                    let iy = y * sh + j;
+                    if iy < ph || iy - ph >= h {
+                        continue;
+                    }
+                    let out = &mut out[(iy - ph) * w..(iy - ph + 1) * w];
+                    let line = kernel_base + y * col_stride[4];
+                    for x in 0..ow {
+                        let ix = x * sw + i;
+                        if ix >= pw && ix - pw < w {
+                            out[ix - pw] += unsafe { *col_ptr.get().add(line + x * col_stride[5]) };
+                        }
+                    }
+                }
+            }
         }
-    }
+    });
 
-    let img = img.slice_dyn(slice_dynamic!(.., .., ph..ph + h, pw..pw + w));
-    img.to_owned_matrix()
+    img
 }
 
 #[cfg(test)]
 mod col2im {
     use zenu_matrix::{
-        matrix::{OwnedMatrix, ToViewMatrix},
+        matrix::{IndexItem, OwnedMatrix, ToViewMatrix},
         matrix_impl::OwnedMatrixDyn,
-        operation::asum::Asum,
+        matrix_iter::MatrixElementIter,
+        operation::{asum::Asum, transpose::TransposeInplace},
     };
 
     use super::col2im;
@@ -82,4 +107,43 @@ mod col2im {
         let ans = OwnedMatrixDyn::from_vec(ans, &[2, 3, 5, 5]);
         assert!((img - ans).asum() < 1e-6);
     }
+
+    #[test]
+    fn col2im_transposed_view_matches_sequential() {
+        // deconv2dと同じく転置されたビューを渡し、加算順が逐次実装と同じことを確認する
+        // 画像がスレッドに分けられる大きさになるようにする
+        let (b, c, h, w) = (3, 24, 32, 30);
+        let (kh, kw, sh, sw, ph, pw) = (3, 3, 2, 2, 1, 1);
+        let (oh, ow) = ((h + 2 * ph - kh) / sh + 1, (w + 2 * pw - kw) / sw + 1);
+        let col = (0..b * c * kh * kw * oh * ow)
+            .map(|x| (x as f32 * 0.11).cos())
+            .collect::<Vec<f32>>();
+        let col = OwnedMatrixDyn::from_vec(col, [c, kh, kw, b, oh, ow]);
+        let col = col.transpose_by_index_inplace(&[3, 0, 1, 2, 4, 5]);
+        let img = col2im(col.to_view(), [b, c, h, w], (kh, kw), (sh, sw), (ph, pw));
+
+        let mut expected = vec![0f32; b * c * h * w];
+        for bi in 0..b {
+            for ci in 0..c {
+                for j in 0..kh {
+                    for i in 0..kw {
+                        for y in 0..oh {
+                            for x in 0..ow {
+                                let (iy, ix) = (y * sh + j, x * sw + i);
+                                if iy < ph || iy - ph >= h || ix < pw || ix - pw >= w {
+                                    continue;
+                                }
+                                expected[((bi * c + ci) * h + iy - ph) * w + ix - pw] +=
+                                    col.index_item([bi, ci, j, i, y, x]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let img = img.to_view();
+        for (idx, value) in img.iter().enumerate() {
+            assert_eq!(value.to_bits(), expected[idx].to_bits());
+        }
+    }
 }
@@ -1,51 +1,23 @@
 use zenu_matrix::{
     constructor::zeros::Zeros,
     dim::DimDyn,
-    matrix::{MatrixBase, MatrixSliceDyn, MatrixSliceMutDyn, ToViewMutMatrix},
+    matrix::{AsMutPtr, AsPtr, MatrixBase, ToViewMutMatrix},
     matrix_impl::{Matrix, OwnedMatrixDyn},
     memory_impl::{OwnedMem, ViewMem},
     num::Num,
-    operation::{
-        copy_from::CopyFrom,
-        reshape::{Reshape, ReshapeMut},
-        transpose::TransposeInplace,
-    },
-    slice_dynamic,
 };
 
-fn padding<T: Num>(
-    input: Matrix<ViewMem<T>, DimDyn>,
-    padding: (usize, usize),
-) -> Matrix<OwnedMem<T>, DimDyn> {
-    let (padding_height, padding_width) = padding;
-    let (batch_size, in_channels, in_height, in_width) = (
-        input.shape()[0],
-        input.shape()[1],
-        input.shape()[2],
-        input.shape()[3],
-    );
-    let out_height = in_height + 2 * padding_height;
-    let out_width = in_width + 2 * padding_width;
-
-    let mut output = OwnedMatrixDyn::zeros([batch_size, in_channels, out_height, out_width]);
-    let mut output_view_mut = output.to_view_mut();
-
-    let mut output_view_mut = output_view_mut.slice_mut_dyn(slice_dynamic!(
-        ..,
-        ..,
-        padding_height..padding_height + in_height,
-        padding_width..padding_width + in_width
-    ));
-    output_view_mut.copy_from(&input);
-
-    output
-}
+use super::parallel::{for_each_chunk, SyncPtr};
 
 pub(super) struct Im2ColRes<T: Num> {
     pub(crate) col: Matrix<OwnedMem<T>, DimDyn>,
     pub(crate) out_size: (usize, usize),
 }
 
+/// `[batch, c, h, w]`の画像を`[c * kh * kw, batch * oh * ow]`の行列に展開する。
+/// パディング済みの画像や中間の6次元行列は作らず、最終的なレイアウトに直接書き込む。
+/// 出力はcaching allocatorから確保した行列に直接書き込むので、学習ループでは同じbufferが
+/// 再利用される。出力の各行は独立しているので行ごとにスレッドに分けて処理する。
 pub(super) fn im2col<T: Num>(
     img: Matrix<ViewMem<T>, DimDyn>,
     kernel_size: (usize, usize),
@@ -65,22 +37,38 @@ pub(super) fn im2col<T: Num>(
     let oh = (h - kh + 2 * ph) / sh + 1;
     let ow = (w - kw + 2 * pw) / sw + 1;
 
-    let img = padding(img, pad);
-    let mut col = OwnedMatrixDyn::zeros([batch_size, c, kh, kw, oh, ow]);
+    let img_stride = img.stride();
+    let (sb, sc, sy, sx) = (img_stride[0], img_stride[1], img_stride[2], img_stride[3]);
+    let img_ptr = SyncPtr::new(img.as_ptr());
 
-    for j in 0..kh {
-        let j_lim = j + sh * oh;
-        for i in 0..kw {
-            let i_lim = i + sw * ow;
-            let mut col = col.slice_mut_dyn(slice_dynamic!(.., .., j, i, .., ..));
-            let img = img.slice_dyn(slice_dynamic!(.., .., j..j_lim;sh, i..i_lim;sw));
-            col.copy_from(&img);
+    let mut col: OwnedMatrixDyn<T> = Zeros::zeros([c * kh * kw, batch_size * oh * ow]);
+    let col_buf = unsafe {
+        std::slice::from_raw_parts_mut(
+            col.to_view_mut().as_mut_ptr(),
+            c * kh * kw * batch_size * oh * ow,
+        )
+    };
+    for_each_chunk(col_buf, batch_size * oh * ow, |row, out| {
+        let (ci, j, i) = (row / (kh * kw), row / kw % kh, row % kw);
+        for b in 0..batch_size {
+            let plane = b * sb + ci * sc;
+            for y in 0..oh {
+                let out = &mut out[(b * oh + y) * ow..(b * oh + y + 1) * ow];
+                let iy = y * sh + j;
+                if iy < ph || iy - ph >= h {
+                    continue;
+                }
+                let line = plane + (iy - ph) * sy;
+                for (x, o) in out.iter_mut().enumerate() {
+                    let ix = x * sw + i;
+                    if ix >= pw && ix - pw < w {
+                        *o = unsafe { *img_ptr.get().add(line + (ix - pw) * sx) };
+                    }
+                }
+            }
         }
-    }
+    });
 
-    let col = col.reshape_mut([batch_size, c * kh * kw, oh * ow]);
-    let col = col.transepose_by_index(&[1, 0, 2]);
-    let col = col.reshape_new_matrix([c * kh * kw, batch_size * oh * ow]);
     Im2ColRes {
         col,
         out_size: (oh, ow),
@@ -90,7 +78,8 @@ pub(super) fn im2col<T: Num>(
 #[cfg(test)]
 mod im2col {
     use zenu_matrix::{
-        matrix::{OwnedMatrix, ToViewMatrix},
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
         matrix_impl::OwnedMatrixDyn,
         operation::{asum::Asum, transpose::Transpose},
     };
@@ -223,4 +212,40 @@ mod im2col {
         ans.transpose();
         assert!((ans - result.col).to_view().asum() < 1e-6);
     }
+
+    #[test]
+    fn im2col_threaded_matches_naive() {
+        // 出力がスレッドに分けられる大きさになるようにする
+        let (b, c, h, w) = (8, 3, 33, 31);
+        let input = (0..b * c * h * w)
+            .map(|x| (x as f64 * 0.37).sin())
+            .collect::<Vec<f64>>();
+        let input = OwnedMatrixDyn::from_vec(input.clone(), [b, c, h, w]);
+        let (kh, kw, sh, sw, ph, pw) = (3, 2, 2, 1, 1, 2);
+        let result = im2col(input.to_view(), (kh, kw), (sh, sw), (ph, pw));
+        let (oh, ow) = result.out_size;
+        assert_eq!(result.col.shape().slice(), [c * kh * kw, b * oh * ow]);
+        for ci in 0..c {
+            for j in 0..kh {
+                for i in 0..kw {
+                    for bi in 0..b {
+                        for y in 0..oh {
+                            for x in 0..ow {
+                                let (iy, ix) = (y * sh + j, x * sw + i);
+                                let expected = if iy < ph || iy - ph >= h || ix < pw || ix - pw >= w
+                                {
+                                    0.
+                                } else {
+                                    input.index_item([bi, ci, iy - ph, ix - pw])
+                                };
+                                let row = (ci * kh + j) * kw + i;
+                                let col = (bi * oh + y) * ow + x;
+                                assert_eq!(result.col.index_item([row, col]), expected);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -26,6 +26,7 @@ mod col2im;
 mod conv2d_impl;
 mod deconv2_impl;
 mod im2col;
+mod parallel;
 
 struct Conv2d<T: Num> {
     kernel: Variable<T>,
@@ -0,0 +1,69 @@
+use rayon::prelude::*;
+use zenu_matrix::num::Num;
+
+/// 1スレッドに任せる最小の要素数。これより小さい仕事は呼び出し元のスレッドで処理する
+const MIN_ELEMENTS_PER_THREAD: usize = 1 << 15;
+
+/// `Num`は`Send`を要求しないので、スレッド間で受け渡すためのポインタのラッパー。
+/// 各スレッドは互いに重ならない領域にだけ書き込む。
+#[derive(Clone, Copy)]
+pub(super) struct SyncPtr<T>(*const T);
+
+impl<T> SyncPtr<T> {
+    pub(super) fn new(ptr: *const T) -> Self {
+        Self(ptr)
+    }
+
+    // クロージャがフィールドではなく構造体ごとキャプチャするようにメソッド経由で取り出す
+    pub(super) fn get(self) -> *const T {
+        self.0
+    }
+}
+
+unsafe impl<T> Send for SyncPtr<T> {}
+unsafe impl<T> Sync for SyncPtr<T> {}
+
+#[derive(Clone, Copy)]
+struct SyncPtrMut<T>(*mut T);
+
+impl<T> SyncPtrMut<T> {
+    fn get(self) -> *mut T {
+        self.0
+    }
+}
+
+unsafe impl<T> Send for SyncPtrMut<T> {}
+unsafe impl<T> Sync for SyncPtrMut<T> {}
+
+/// `buf`を`chunk_len`ごとに区切り、`f(chunk_index, chunk)`をrayonのスレッドプールで呼ぶ。
+/// 各チャンクはちょうど一度だけ処理され、処理順は結果に影響しない前提。
+pub(super) fn for_each_chunk<T, F>(buf: &mut [T], chunk_len: usize, f: F)
+where
+    T: Num,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    if chunk_len == 0 || buf.is_empty() {
+        return;
+    }
+    assert_eq!(buf.len() % chunk_len, 0);
+    let num_chunks = buf.len() / chunk_len;
+
+    if buf.len() < 2 * MIN_ELEMENTS_PER_THREAD {
+        for (idx, chunk) in buf.chunks_mut(chunk_len).enumerate() {
+            f(idx, chunk);
+        }
+        return;
+    }
+
+    // `T`は`Send`とは限らないので、スライスではなくチャンクの番号を分配する
+    let ptr = SyncPtrMut(buf.as_mut_ptr());
+    (0..num_chunks)
+        .into_par_iter()
+        .with_min_len((MIN_ELEMENTS_PER_THREAD / chunk_len).max(1))
+        .for_each(|idx| {
+            let chunk = unsafe {
+                std::slice::from_raw_parts_mut(ptr.get().add(idx * chunk_len), chunk_len)
+            };
+            f(idx, chunk);
+        });
+}
@@ -0,0 +1,239 @@
+//! Differentiable operations built from closures.
+//!
+//! [`custom_function`] takes a forward closure on matrices and a backward closure on
+//! [`Variable`]s and does the graph wiring: it creates the outputs, records the op as their
+//! creator and routes the accumulated output gradients into the backward closure. Because the
+//! backward closure works on variables, gradients of custom ops can be differentiated again.
+//!
+//! ```
+//! use zenu_autograd::{creator::from_vec::from_vec, functions::custom::custom_function};
+//! use zenu_matrix::matrix::{IndexItem, ToViewMatrix};
+//!
+//! let x = from_vec(vec![1., 2.], [2]);
+//! let y = from_vec(vec![3., 4.], [2]);
+//! // (x * y, x - y)
+//! let outputs = custom_function(
+//!     "MulSub",
+//!     &[x.clone(), y.clone()],
+//!     |inputs| {
+//!         vec![
+//!             inputs[0].to_view() * inputs[1].to_view(),
+//!             inputs[0].to_view() - inputs[1].to_view(),
+//!         ]
+//!     },
+//!     |inputs, _outputs, grads| {
+//!         let (x, y) = (inputs[0].clone(), inputs[1].clone());
+//!         let (g_mul, g_sub) = (grads[0].clone(), grads[1].clone());
+//!         vec![
+//!             Some(g_mul.clone() * y + g_sub.clone()),
+//!             Some(g_mul * x - g_sub),
+//!         ]
+//!     },
+//! );
+//! let loss = outputs[0].clone() + outputs[1].clone();
+//! loss.backward();
+//! assert_eq!(x.get_grad().unwrap().get_data().index_item([1]), 5.);
+//! assert_eq!(y.get_grad().unwrap().get_data().index_item([1]), 1.);
+//! ```
+use std::{cell::RefCell, rc::Rc};
+
+use zenu_matrix::{
+    dim::DimDyn, matrix::MatrixBase, matrix_impl::Matrix, memory_impl::OwnedMem, num::Num,
+    operation::copy_from::CopyFrom,
+};
+
+use crate::{creator::zeros::zeros, Function, Variable, VariableWeak};
+
+type ForwardFn<T> = dyn Fn(&[Matrix<OwnedMem<T>, DimDyn>]) -> Vec<Matrix<OwnedMem<T>, DimDyn>>;
+type BackwardFn<T> =
+    dyn Fn(&[Variable<T>], &[Option<Variable<T>>], &[Variable<T>]) -> Vec<Option<Variable<T>>>;
+
+struct CustomFunction<T: Num> {
+    name: &'static str,
+    inputs: Vec<Variable<T>>,
+    outputs: Vec<VariableWeak<T>>,
+    output_shapes: Vec<DimDyn>,
+    forward: Box<ForwardFn<T>>,
+    backward: Box<BackwardFn<T>>,
+}
+
+impl<T: Num> Function<T> for CustomFunction<T> {
+    fn forward(&self) {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| input.get_data())
+            .collect::<Vec<_>>();
+        let results = (self.forward)(&inputs);
+        for (output, result) in self.outputs.iter().zip(results) {
+            if let Some(output) = output.upgrade() {
+                output.get_data_mut().copy_from(&result);
+            }
+        }
+    }
+
+    fn backward(&self) {
+        let outputs = self
+            .outputs
+            .iter()
+            .map(VariableWeak::upgrade)
+            .collect::<Vec<_>>();
+        let grads = outputs
+            .iter()
+            .zip(&self.output_shapes)
+            .map(|(output, &shape)| {
+                output
+                    .as_ref()
+                    .and_then(Variable::get_grad)
+                    .unwrap_or_else(|| zeros(shape))
+            })
+            .collect::<Vec<_>>();
+
+        let input_grads = (self.backward)(&self.inputs, &outputs, &grads);
+        assert_eq!(
+            input_grads.len(),
+            self.inputs.len(),
+            "backward of {} must return one gradient per input",
+            self.name
+        );
+        for (input, grad) in self.inputs.iter().zip(input_grads) {
+            if let Some(grad) = grad {
+                input.set_grad(grad);
+            }
+        }
+    }
+
+    fn get_inputs(&self) -> Vec<Variable<T>> {
+        self.inputs.clone()
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Applies a user-defined differentiable operation to `inputs` and returns its outputs.
+///
+/// * `forward` receives the input data and returns the output data; it runs once here.
+/// * `backward` receives the inputs, the outputs and the gradient of every output, and returns
+///   one gradient per input, `None` for inputs that get no gradient. An output that no longer
+///   exists is passed as `None` and its gradient as zeros, as are gradients of outputs that
+///   did not take part in the loss.
+///
+/// `name` is reported by [`Function::name`], for example in [`Variable::to_dot`].
+pub fn custom_function<T, F, B>(
+    name: &'static str,
+    inputs: &[Variable<T>],
+    forward: F,
+    backward: B,
+) -> Vec<Variable<T>>
+where
+    T: Num,
+    F: Fn(&[Matrix<OwnedMem<T>, DimDyn>]) -> Vec<Matrix<OwnedMem<T>, DimDyn>> + 'static,
+    B: Fn(&[Variable<T>], &[Option<Variable<T>>], &[Variable<T>]) -> Vec<Option<Variable<T>>>
+        + 'static,
+{
+    let data = inputs
+        .iter()
+        .map(|input| input.get_data())
+        .collect::<Vec<_>>();
+    let outputs = forward(&data)
+        .into_iter()
+        .map(Variable::new)
+        .collect::<Vec<_>>();
+
+    let function = CustomFunction {
+        name,
+        inputs: inputs.to_vec(),
+        outputs: outputs
+            .iter()
+            .map(|output| output.clone().downgrade())
+            .collect(),
+        output_shapes: outputs
+            .iter()
+            .map(|output| output.get_data().shape())
+            .collect(),
+        forward: Box::new(forward),
+        backward: Box::new(backward),
+    };
+    let function: Rc<RefCell<Box<dyn Function<T>>>> = Rc::new(RefCell::new(Box::new(function)));
+    for output in &outputs {
+        output.set_creator(function.clone());
+    }
+    outputs
+}
+
+#[cfg(test)]
+mod custom {
+    use zenu_matrix::{
+        matrix::{IndexItem, ToViewMatrix, ToViewMutMatrix},
+        operation::exp::ExpAssign,
+    };
+
+    use crate::{
+        creator::from_vec::from_vec, functions::exp::exp, grad, gradcheck::gradcheck, Variable,
+    };
+
+    use super::custom_function;
+
+    /// (exp(x), x * y)
+    fn exp_and_mul(x: Variable<f64>, y: Variable<f64>) -> Vec<Variable<f64>> {
+        custom_function(
+            "ExpAndMul",
+            &[x, y],
+            |inputs| {
+                let mut e = inputs[0].clone();
+                e.to_view_mut().exp_assign(&inputs[0]);
+                vec![e, inputs[0].to_view() * inputs[1].to_view()]
+            },
+            |inputs, outputs, grads| {
+                let gx = grads[1].clone() * inputs[1].clone();
+                // exp(x)が捨てられていればその勾配は0
+                let gx = match &outputs[0] {
+                    Some(e) => grads[0].clone() * e.clone() + gx,
+                    None => gx,
+                };
+                vec![Some(gx), Some(grads[1].clone() * inputs[0].clone())]
+            },
+        )
+    }
+
+    #[test]
+    fn multiple_outputs_gradcheck() {
+        let x = from_vec(vec![0.5, -1., 2.], [3]);
+        let y = from_vec(vec![1.5, 0.25, -2.], [3]);
+        gradcheck(
+            |v| {
+                let out = exp_and_mul(v[0].clone(), v[1].clone());
+                out[0].clone() + out[1].clone()
+            },
+            &[x, y],
+            1e-6,
+            1e-6,
+            1e-4,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn unused_output() {
+        let x = from_vec(vec![1., 2.], [2]);
+        let y = from_vec(vec![3., 4.], [2]);
+        let mul = exp_and_mul(x.clone(), y.clone()).remove(1);
+        mul.backward();
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 3.);
+        assert_eq!(y.get_grad().unwrap().get_data().index_item([1]), 2.);
+        assert!(mul.to_dot().contains("label=\"ExpAndMul\""));
+    }
+
+    #[test]
+    fn second_order() {
+        let x = from_vec(vec![0.5], [1]);
+        let y = from_vec(vec![2.], [1]);
+        let e = exp_and_mul(x.clone(), y).remove(0);
+        let gx = grad(&[e], std::slice::from_ref(&x)).remove(0);
+        let ggx = grad(&[gx], std::slice::from_ref(&x)).remove(0);
+        let ans = exp(x).get_data().index_item([0]);
+        assert!((ggx.get_data().index_item([0]) - ans).abs() < 1e-12);
+    }
+}
@@ -15,6 +15,7 @@ pub mod broadcast;
 pub mod clip;
 pub mod conv2d;
 pub mod cosh;
+pub mod custom;
 pub mod div;
 pub mod exp;
 pub mod flatten;
@@ -24,7 +25,9 @@ pub mod matmul;
 pub mod powf;
 pub mod reshape;
 pub mod sinh;
+pub mod slice;
 pub mod softmax;
+pub mod stop_gradient;
 pub mod sub;
 pub mod sum;
 pub mod sum_to;
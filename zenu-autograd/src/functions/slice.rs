@@ -0,0 +1,214 @@
+use std::{cell::RefCell, rc::Rc};
+
+use zenu_matrix::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, MatrixSliceDyn, MatrixSliceMutDyn},
+    num::Num,
+    operation::copy_from::CopyFrom,
+    slice::{dynamic::Slice, slice_dim::SliceDim},
+};
+
+use crate::{creator::zeros::zeros, Function, Variable, VariableWeak};
+
+struct SliceFn<T: Num> {
+    input: Variable<T>,
+    index: Slice,
+    output: VariableWeak<T>,
+}
+
+/// Scatters `grad_output` into a zero tensor of the sliced input's shape.
+struct SliceGrad<T: Num> {
+    grad_output: Variable<T>,
+    index: Slice,
+    output: VariableWeak<T>,
+}
+
+impl<T: Num> Function<T> for SliceFn<T> {
+    fn forward(&self) {
+        let input = self.input.get_data();
+        let output = self.output.upgrade().unwrap();
+        output
+            .get_data_mut()
+            .copy_from(&input.slice_dyn(self.index));
+    }
+
+    fn backward(&self) {
+        let output_grad = self.output.upgrade().unwrap().get_grad().unwrap();
+        let input_shape = self.input.get_data().shape();
+        self.input
+            .set_grad(slice_grad(output_grad, self.index, input_shape));
+    }
+
+    fn get_inputs(&self) -> Vec<Variable<T>> {
+        vec![self.input.clone()]
+    }
+}
+
+impl<T: Num> Function<T> for SliceGrad<T> {
+    fn forward(&self) {
+        let grad_output = self.grad_output.get_data();
+        let output = self.output.upgrade().unwrap();
+        let mut output = output.get_data_mut();
+        output.slice_mut_dyn(self.index).copy_from(&grad_output);
+    }
+
+    fn backward(&self) {
+        let output_grad = self.output.upgrade().unwrap().get_grad().unwrap();
+        self.grad_output.set_grad(slice(output_grad, self.index));
+    }
+
+    fn get_inputs(&self) -> Vec<Variable<T>> {
+        vec![self.grad_output.clone()]
+    }
+}
+
+fn slice_grad<T: Num>(grad_output: Variable<T>, index: Slice, input_shape: DimDyn) -> Variable<T> {
+    let output = zeros(input_shape);
+    let slice_grad = SliceGrad {
+        grad_output,
+        index,
+        output: output.clone().downgrade(),
+    };
+    slice_grad.forward();
+    output.set_creator(Rc::new(RefCell::new(Box::new(slice_grad))));
+    output
+}
+
+/// Differentiable [`MatrixSliceDyn::slice_dyn`]. `index` must have one entry per axis, as
+/// built by `slice_dynamic!`; an integer entry selects one position and drops the axis. The
+/// gradient is scattered back into zeros of the input's shape.
+pub fn slice<T: Num>(x: Variable<T>, index: Slice) -> Variable<T> {
+    let input_shape = x.get_data().shape();
+    assert_eq!(
+        index.len,
+        input_shape.len(),
+        "slice must have one entry per axis"
+    );
+    let output_shape = x.get_data().slice_dyn(index).shape();
+    let output = zeros(output_shape);
+    let slice = SliceFn {
+        input: x,
+        index,
+        output: output.clone().downgrade(),
+    };
+    slice.forward();
+    output.set_creator(Rc::new(RefCell::new(Box::new(slice))));
+    output
+}
+
+/// Selects position `index` along `axis`, removing that axis.
+pub fn index_axis<T: Num>(x: Variable<T>, axis: usize, index: usize) -> Variable<T> {
+    let shape = x.get_data().shape();
+    assert!(axis < shape.len(), "axis out of range");
+    assert!(index < shape[axis], "index out of range");
+    slice(x, axis_slice(shape.len(), axis, SliceDim::from(index)))
+}
+
+/// The `len` elements along `axis` starting at `start`. The number of axes is unchanged.
+pub fn narrow<T: Num>(x: Variable<T>, axis: usize, start: usize, len: usize) -> Variable<T> {
+    let shape = x.get_data().shape();
+    assert!(axis < shape.len(), "axis out of range");
+    assert!(len > 0 && start + len <= shape[axis], "narrow out of range");
+    slice(
+        x,
+        axis_slice(shape.len(), axis, SliceDim::from(start..start + len)),
+    )
+}
+
+pub(crate) fn axis_slice(ndim: usize, axis: usize, dim: SliceDim) -> Slice {
+    let mut index = vec![SliceDim::from(..); ndim];
+    index[axis] = dim;
+    Slice::from(index.as_slice())
+}
+
+#[cfg(test)]
+mod slice {
+    use zenu_matrix::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::asum::Asum,
+        slice_dynamic,
+    };
+
+    use crate::{creator::from_vec::from_vec, grad, gradcheck::gradcheck, Variable};
+
+    use super::{index_axis, narrow, slice};
+
+    fn arange(shape: &[usize]) -> Variable<f64> {
+        let n = shape.iter().product::<usize>();
+        from_vec((0..n).map(|x| x as f64).collect(), shape)
+    }
+
+    #[test]
+    fn slice_forward_backward() {
+        let x = arange(&[3, 4]);
+        let y = slice(x.clone(), slice_dynamic!(1.., ..;2));
+        let ans = OwnedMatrixDyn::from_vec(vec![4., 6., 8., 10.], [2, 2]);
+        assert!((y.get_data().to_view() - ans.to_view()).asum() < 1e-12);
+
+        y.backward();
+        let grad_ans =
+            OwnedMatrixDyn::from_vec(vec![0., 0., 0., 0., 1., 0., 1., 0., 1., 0., 1., 0.], [3, 4]);
+        let grad = x.get_grad().unwrap().get_data();
+        assert!((grad.to_view() - grad_ans.to_view()).asum() < 1e-12);
+    }
+
+    #[test]
+    fn index_axis_drops_axis() {
+        let x = arange(&[2, 3, 4]);
+        let y = index_axis(x.clone(), 1, 2);
+        assert_eq!(y.get_data().shape().slice(), [2, 4]);
+        assert_eq!(y.get_data().index_item([1, 3]), 23.);
+
+        let y = y * from_vec(vec![1., 2., 3., 4., 5., 6., 7., 8.], [2, 4]);
+        y.backward();
+        let grad = x.get_grad().unwrap().get_data();
+        assert_eq!(grad.index_item([1, 2, 3]), 8.);
+        assert_eq!(grad.index_item([1, 1, 3]), 0.);
+    }
+
+    #[test]
+    fn narrow_keeps_axis() {
+        let x = arange(&[2, 5]);
+        let y = narrow(x, 1, 1, 3);
+        assert_eq!(y.get_data().shape().slice(), [2, 3]);
+        assert_eq!(y.get_data().index_item([1, 0]), 6.);
+    }
+
+    #[test]
+    fn gradcheck_slices() {
+        let x = from_vec(vec![0.5, -1., 2., 0.3, 1.1, -0.7], [2, 3]);
+        gradcheck(
+            |v| {
+                let a = narrow(v[0].clone(), 1, 0, 2);
+                let b = index_axis(v[0].clone(), 0, 1);
+                let b = slice(b, slice_dynamic!(1..));
+                a.clone() * a * b
+            },
+            &[x],
+            1e-6,
+            1e-6,
+            1e-4,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn second_order_through_scatter() {
+        let x = from_vec(vec![1., 2., 3.], [3]);
+        let y = narrow(x.clone(), 0, 1, 2);
+        let y = y.clone() * y.clone() * y;
+        let gx = grad(&[y], std::slice::from_ref(&x)).remove(0);
+        let ggx = grad(&[gx], std::slice::from_ref(&x)).remove(0);
+        // 6x on the narrowed part, zero elsewhere
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 12., 18.], [3]);
+        assert!((ggx.get_data().to_view() - ans.to_view()).asum() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of range")]
+    fn index_axis_out_of_range() {
+        index_axis(arange(&[2, 3]), 1, 3);
+    }
+}
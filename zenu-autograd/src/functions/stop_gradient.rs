@@ -0,0 +1,108 @@
+use std::{cell::RefCell, rc::Rc};
+
+use zenu_matrix::num::Num;
+
+use crate::{Function, Variable};
+
+/// 入力の`Variable`は持たない。持つと上流のグラフがbackwardで解放されず残ってしまう
+struct StopGradient {
+    gen: usize,
+}
+
+impl<T: Num> Function<T> for StopGradient {
+    // 出力は作るときに入力のデータをコピー済み
+    fn forward(&self) {}
+
+    fn backward(&self) {}
+
+    // 入力を返すと上流の関数までbackwardが辿ってしまうので、グラフはここで終わりにする
+    fn get_inputs(&self) -> Vec<Variable<T>> {
+        Vec::new()
+    }
+
+    fn get_gen(&self) -> usize {
+        self.gen
+    }
+}
+
+/// Identity in the forward pass that passes no gradient back to `input`.
+///
+/// Unlike [`Variable::detach`] the op stays in the graph and shows up in [`Variable::to_dot`],
+/// but it keeps no reference to `input` and reports no inputs: backward, graph traversal and
+/// the trainable-variable search all end here, as if `input` were a constant. Trainable
+/// variables upstream of the op are therefore not returned by
+/// [`Variable::get_all_trainable_variables`], since they receive no gradient through it.
+pub fn stop_gradient<T: Num>(input: Variable<T>) -> Variable<T> {
+    let output = Variable::new(input.get_data());
+    let stop_gradient = StopGradient {
+        gen: input.get_gen(),
+    };
+    output.set_creator(Rc::new(RefCell::new(Box::new(stop_gradient))));
+    output
+}
+
+#[cfg(test)]
+mod stop_gradient {
+    use zenu_matrix::matrix::IndexItem;
+
+    use crate::creator::from_vec::from_vec;
+
+    use super::stop_gradient;
+
+    #[test]
+    fn blocks_one_path() {
+        let x = from_vec(vec![3.], [1]);
+        // d/dx (x * sg(x)) = sg(x)
+        let y = x.clone() * stop_gradient(x.clone());
+        assert_eq!(y.get_data().index_item([0]), 9.);
+        y.backward();
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 3.);
+        assert!(y.to_dot().contains("label=\"StopGradient\""));
+    }
+
+    #[test]
+    fn blocks_everything() {
+        let x = from_vec(vec![1., 2.], [2]);
+        let y = stop_gradient(x.clone() * x.clone());
+        y.backward();
+        assert!(x.get_grad().is_none());
+    }
+
+    #[test]
+    fn upstream_is_released_and_not_trainable() {
+        let w = from_vec(vec![2.], [1]);
+        w.set_is_train(true);
+        let v = from_vec(vec![3.], [1]);
+        v.set_is_train(true);
+        let hidden = w.clone() * w.clone();
+        let weak_hidden = hidden.clone().downgrade();
+        let y = stop_gradient(hidden) * v.clone();
+
+        // 勾配が流れないwはパラメータとして見つからない
+        let params = y.get_all_trainable_variables();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].get_data().index_item([0]), 3.);
+
+        // stop_gradientより上流のグラフはどこからも参照されない
+        assert!(weak_hidden.upgrade().is_none());
+        y.backward();
+        assert_eq!(v.get_grad().unwrap().get_data().index_item([0]), 4.);
+        assert!(w.get_grad().is_none());
+    }
+
+    #[test]
+    fn detach_cuts_graph() {
+        let x = from_vec(vec![2.], [1]);
+        x.set_is_train(true);
+        let y = x.clone() * x.clone();
+        let target = y.detach();
+        assert!(target.get_creator().is_none());
+        assert!(!target.get_is_train());
+        assert_eq!(target.get_data().index_item([0]), 4.);
+
+        // 4x - x^2 の勾配はtargetを定数として扱う
+        let loss = target * x.clone() - y;
+        loss.backward();
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 0.);
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use zenu_matrix::{constructor::ones::Ones, matrix::MatrixBase, num::Num};
+
+use crate::{creator::zeros::zeros, run_backward, set_grad_enabled, Variable};
+
+/// Gradients of the sum of `outputs` with respect to each of `inputs`, with the backward pass
+/// recorded so the results can be differentiated again (gradient penalties, Hessian-vector
+/// products). Shorthand for [`grad_with`] with `create_graph` and `retain_graph` set.
+///
+/// The `.grad` of every variable in the graph is left exactly as it was.
+pub fn grad<T: Num>(outputs: &[Variable<T>], inputs: &[Variable<T>]) -> Vec<Variable<T>> {
+    grad_with(outputs, inputs, true, true)
+}
+
+/// Gradients of the sum of `outputs` with respect to each of `inputs`, in the order of
+/// `inputs`. Inputs the outputs do not depend on get zeros.
+///
+/// Unlike [`Variable::backward_with`] this does not accumulate into `.grad`: the existing
+/// gradients are put aside for the duration of the pass and restored afterwards.
+pub fn grad_with<T: Num>(
+    outputs: &[Variable<T>],
+    inputs: &[Variable<T>],
+    create_graph: bool,
+    retain_graph: bool,
+) -> Vec<Variable<T>> {
+    let variables = graph_variables(outputs, inputs);
+    let saved = variables
+        .iter()
+        .map(|variable| variable.inner.borrow_mut().grad.take())
+        .collect::<Vec<_>>();
+
+    for output in outputs {
+        let ones = Variable::new(Ones::ones(output.get_data().shape()));
+        match output.get_grad() {
+            Some(prev) => output.inner.borrow_mut().grad = Some(prev + ones),
+            None => output.inner.borrow_mut().grad = Some(ones),
+        }
+    }
+
+    {
+        let _guard = set_grad_enabled(create_graph);
+        run_backward(outputs, retain_graph);
+    }
+
+    let grads = inputs
+        .iter()
+        .map(|input| {
+            input
+                .get_grad()
+                .unwrap_or_else(|| zeros(input.get_data().shape()))
+        })
+        .collect();
+
+    for (variable, grad) in variables.iter().zip(saved) {
+        variable.inner.borrow_mut().grad = grad;
+    }
+    grads
+}
+
+/// Every distinct variable reachable from `outputs`, plus `inputs`.
+fn graph_variables<T: Num>(outputs: &[Variable<T>], inputs: &[Variable<T>]) -> Vec<Variable<T>> {
+    let mut seen_var = HashSet::new();
+    let mut seen_func = HashSet::new();
+    let mut variables = Vec::new();
+    let mut stack = outputs.iter().chain(inputs).cloned().collect::<Vec<_>>();
+
+    while let Some(variable) = stack.pop() {
+        if !seen_var.insert(variable.inner.as_ptr()) {
+            continue;
+        }
+        if let Some(creator) = variable.get_creator() {
+            if seen_func.insert(creator.as_ptr()) {
+                stack.extend(creator.borrow().get_inputs());
+            }
+        }
+        variables.push(variable);
+    }
+    variables
+}
+
+#[cfg(test)]
+mod grad {
+    use zenu_matrix::{
+        matrix::{IndexItem, OwnedMatrix},
+        matrix_impl::OwnedMatrixDyn,
+    };
+
+    use crate::{creator::from_vec::from_vec, functions::sum::sum, Variable};
+
+    use super::grad;
+
+    fn cube(x: Variable<f64>) -> Variable<f64> {
+        x.clone() * x.clone() * x
+    }
+
+    #[test]
+    fn first_order_leaves_grad_untouched() {
+        let x = from_vec(vec![1., 2.], [2]);
+        let y = cube(x.clone());
+        let gx = grad(std::slice::from_ref(&y), std::slice::from_ref(&x));
+        assert_eq!(gx[0].get_data().index_item([0]), 3.);
+        assert_eq!(gx[0].get_data().index_item([1]), 12.);
+        assert!(x.get_grad().is_none());
+        assert!(y.get_grad().is_none());
+    }
+
+    #[test]
+    fn second_order() {
+        let x = from_vec(vec![1., 2.], [2]);
+        let y = cube(x.clone());
+        let gx = grad(&[y], std::slice::from_ref(&x)).remove(0);
+        let ggx = grad(&[gx], std::slice::from_ref(&x)).remove(0);
+        // d2/dx2 x^3 = 6x
+        assert_eq!(ggx.get_data().index_item([0]), 6.);
+        assert_eq!(ggx.get_data().index_item([1]), 12.);
+    }
+
+    #[test]
+    fn backward_with_create_graph() {
+        let x = from_vec(vec![3.], [1]);
+        let y = cube(x.clone());
+        y.backward_with(true, true);
+        let gx = x.get_grad().unwrap();
+        assert_eq!(gx.get_data().index_item([0]), 27.);
+        assert!(gx.get_creator().is_some());
+
+        // 途中の変数にも1階微分の勾配が残っているのでグラフ全体の勾配を消す
+        y.clear_grad();
+        let gx = sum(gx, 0, false);
+        gx.backward();
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 18.);
+    }
+
+    #[test]
+    fn backward_without_create_graph() {
+        let x = from_vec(vec![3.], [1]);
+        let y = cube(x.clone());
+        y.backward_with(false, true);
+        assert!(x.get_grad().unwrap().get_creator().is_none());
+    }
+
+    #[test]
+    fn backward_records_graph_by_default() {
+        let x = from_vec(vec![3.], [1]);
+        let y = cube(x.clone());
+        y.backward();
+        let gx = x.get_grad().unwrap();
+        assert!(gx.get_creator().is_some());
+
+        y.clear_grad();
+        sum(gx, 0, false).backward();
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 18.);
+    }
+
+    #[test]
+    fn multiple_outputs_and_unused_input() {
+        let x = from_vec(vec![1., 2.], [2]);
+        let unused = Variable::new(OwnedMatrixDyn::from_vec(vec![5.], [1]));
+        let a = x.clone() * x.clone();
+        let b = x.clone() * from_vec(vec![3., 3.], [2]);
+        let g = grad(&[a, b], &[x, unused]);
+        // 2x + 3
+        assert_eq!(g[0].get_data().index_item([0]), 5.);
+        assert_eq!(g[0].get_data().index_item([1]), 7.);
+        assert_eq!(g[1].get_data().index_item([0]), 0.);
+    }
+}
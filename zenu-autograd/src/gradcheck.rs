@@ -0,0 +1,235 @@
+//! Finite-difference gradient checking.
+//!
+//! [`gradcheck`] differentiates `f` with [`grad_with`] and compares every element of the
+//! result with the central difference `(f(x + eps) - f(x - eps)) / 2eps`. Outputs that are not
+//! scalars are summed first. Everything is evaluated in `f64` so the check is not dominated by
+//! rounding noise.
+//!
+//! ```
+//! use zenu_autograd::{creator::from_vec::from_vec, functions::tanh::tanh, gradcheck::gradcheck};
+//!
+//! let x = from_vec(vec![-0.5, 0.1, 0.7], [3]);
+//! gradcheck(|inputs| tanh(inputs[0].clone()), &[x], 1e-6, 1e-5, 1e-3).unwrap();
+//! ```
+use std::fmt::{Display, Formatter};
+
+use zenu_matrix::{
+    dim::{DimDyn, DimTrait},
+    matrix::{IndexItem, IndexItemAsign, MatrixBase, ToViewMatrix, ToViewMutMatrix},
+    matrix_iter::MatrixElementIter,
+};
+
+use crate::{grad_with, no_grad, Variable};
+
+/// The element whose analytic and numerical gradients disagree the most.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradCheckMismatch {
+    /// Position of the offending input in `inputs`.
+    pub input: usize,
+    /// Index of the element inside that input.
+    pub index: Vec<usize>,
+    pub analytic: f64,
+    pub numerical: f64,
+}
+
+impl Display for GradCheckMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gradient mismatch at input {} index {:?}: analytic {} vs numerical {} (diff {})",
+            self.input,
+            self.index,
+            self.analytic,
+            self.numerical,
+            (self.analytic - self.numerical).abs()
+        )
+    }
+}
+
+impl std::error::Error for GradCheckMismatch {}
+
+/// Checks the gradients of `f` at `inputs`.
+///
+/// An element passes when `|analytic - numerical| <= atol + rtol * |numerical|`. On failure
+/// the element that exceeds its tolerance by the largest margin is returned. The data of
+/// `inputs` is restored after each perturbation and their `.grad` is not touched.
+pub fn gradcheck<F>(
+    f: F,
+    inputs: &[Variable<f64>],
+    eps: f64,
+    atol: f64,
+    rtol: f64,
+) -> Result<(), GradCheckMismatch>
+where
+    F: Fn(&[Variable<f64>]) -> Variable<f64>,
+{
+    assert!(eps > 0., "eps must be positive");
+
+    let output = f(inputs);
+    let analytic = grad_with(&[output], inputs, false, true);
+
+    let mut worst: Option<(f64, GradCheckMismatch)> = None;
+    for (input_idx, input) in inputs.iter().enumerate() {
+        let shape = input.get_data().shape();
+        let analytic = analytic[input_idx].get_data();
+        for flat in 0..shape.num_elm() {
+            let index = unravel_index(flat, shape);
+            let numerical = central_difference(&f, inputs, input, index, eps);
+            let analytic = analytic.index_item(index);
+
+            let excess = (analytic - numerical).abs() - (atol + rtol * numerical.abs());
+            // NaNは常に不一致として扱う
+            let excess = if excess.is_nan() {
+                f64::INFINITY
+            } else {
+                excess
+            };
+            if excess > 0. && worst.as_ref().is_none_or(|(w, _)| excess > *w) {
+                worst = Some((
+                    excess,
+                    GradCheckMismatch {
+                        input: input_idx,
+                        index: index.slice().to_vec(),
+                        analytic,
+                        numerical,
+                    },
+                ));
+            }
+        }
+    }
+
+    match worst {
+        Some((_, mismatch)) => Err(mismatch),
+        None => Ok(()),
+    }
+}
+
+fn central_difference<F>(
+    f: &F,
+    inputs: &[Variable<f64>],
+    input: &Variable<f64>,
+    index: DimDyn,
+    eps: f64,
+) -> f64
+where
+    F: Fn(&[Variable<f64>]) -> Variable<f64>,
+{
+    let _guard = no_grad();
+    let original = input.get_data().index_item(index);
+
+    set_item(input, index, original + eps);
+    let plus = sum_all(&f(inputs));
+    set_item(input, index, original - eps);
+    let minus = sum_all(&f(inputs));
+    set_item(input, index, original);
+
+    (plus - minus) / (2. * eps)
+}
+
+fn set_item(input: &Variable<f64>, index: DimDyn, value: f64) {
+    input
+        .get_data_mut()
+        .to_view_mut()
+        .index_item_asign(index, value);
+}
+
+fn sum_all(variable: &Variable<f64>) -> f64 {
+    variable.get_data().to_view().iter().sum()
+}
+
+fn unravel_index(mut flat: usize, shape: DimDyn) -> DimDyn {
+    let mut index = vec![0; shape.len()];
+    for axis in (0..shape.len()).rev() {
+        index[axis] = flat % shape[axis];
+        flat /= shape[axis];
+    }
+    DimDyn::from(index.as_slice())
+}
+
+#[cfg(test)]
+mod gradcheck {
+    use std::{cell::RefCell, rc::Rc};
+
+    use zenu_matrix::operation::basic_operations::MatrixAddAssign;
+
+    use crate::{
+        creator::{from_vec::from_vec, zeros::zeros_like},
+        functions::{matmul::matmul, sum::sum, tanh::tanh},
+        Function, Variable, VariableWeak,
+    };
+
+    use super::gradcheck;
+
+    #[test]
+    fn elementwise_ops() {
+        let x = from_vec(vec![0.3, -1.2, 2.0, 0.5], [2, 2]);
+        let y = from_vec(vec![1.5, 0.7, -0.4, 2.2], [2, 2]);
+        gradcheck(
+            |v| tanh(v[0].clone() * v[1].clone()) + v[0].clone() / v[1].clone(),
+            &[x, y],
+            1e-6,
+            1e-6,
+            1e-4,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn matmul_and_sum() {
+        let a = from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let b = from_vec(vec![0.5, -1., 2., 0.25, -0.75, 1.5], [3, 2]);
+        gradcheck(
+            |v| sum(matmul(v[0].clone(), v[1].clone()), 0, false),
+            &[a.clone(), b],
+            1e-6,
+            1e-6,
+            1e-4,
+        )
+        .unwrap();
+        assert!(a.get_grad().is_none());
+    }
+
+    /// わざと勾配を半分にした二乗
+    struct BrokenSquare {
+        input: Variable<f64>,
+        output: VariableWeak<f64>,
+    }
+
+    impl Function<f64> for BrokenSquare {
+        fn forward(&self) {
+            let x = self.input.get_data();
+            let output = self.output.upgrade().unwrap();
+            output.get_data_mut().add_assign(x.clone() * x);
+        }
+
+        fn backward(&self) {
+            let grad = self.output.upgrade().unwrap().get_grad().unwrap();
+            self.input.set_grad(grad * self.input.clone());
+        }
+
+        fn get_inputs(&self) -> Vec<Variable<f64>> {
+            vec![self.input.clone()]
+        }
+    }
+
+    fn broken_square(x: Variable<f64>) -> Variable<f64> {
+        let output = zeros_like(&x);
+        let square = BrokenSquare {
+            input: x,
+            output: output.clone().downgrade(),
+        };
+        square.forward();
+        output.set_creator(Rc::new(RefCell::new(Box::new(square))));
+        output
+    }
+
+    #[test]
+    fn reports_worst_mismatch() {
+        let x = from_vec(vec![1., -3., 2.], [3]);
+        let err = gradcheck(|v| broken_square(v[0].clone()), &[x], 1e-6, 1e-6, 1e-4).unwrap_err();
+        assert_eq!(err.input, 0);
+        assert_eq!(err.index, vec![1]);
+        assert!((err.analytic + 3.).abs() < 1e-9);
+        assert!((err.numerical + 6.).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,163 @@
+use std::rc::Rc;
+
+use zenu_matrix::num::Num;
+
+use crate::{Variable, VariableWeak};
+
+/// Called with each incoming gradient of a variable. Returning `Some` replaces the gradient.
+pub(crate) type GradHook<T> = Rc<dyn Fn(&Variable<T>) -> Option<Variable<T>>>;
+
+/// Handle returned by [`Variable::register_hook`]. Dropping it keeps the hook registered;
+/// call [`HookHandle::remove`] to unregister it.
+pub struct HookHandle<T: Num> {
+    variable: VariableWeak<T>,
+    id: usize,
+}
+
+impl<T: Num> HookHandle<T> {
+    /// Unregisters the hook. Does nothing if the variable no longer exists.
+    pub fn remove(self) {
+        if let Some(variable) = self.variable.upgrade() {
+            variable
+                .inner
+                .borrow_mut()
+                .hooks
+                .retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+impl<T: Num> Variable<T> {
+    /// Registers `hook` to run every time a gradient is accumulated into this variable by
+    /// [`Variable::set_grad`], before it is added to the existing gradient.
+    ///
+    /// The hook receives the incoming gradient and can return a replacement, for example a
+    /// clipped copy, or `None` to leave it unchanged. Hooks run in registration order, each
+    /// seeing the result of the previous one. A replacement must have the shape of the
+    /// variable, otherwise [`Variable::set_grad`] panics.
+    ///
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    ///
+    /// use zenu_autograd::creator::from_vec::from_vec;
+    /// use zenu_matrix::{matrix::IndexItem, operation::clip::Clip};
+    ///
+    /// let x = from_vec(vec![2.], [1]);
+    /// let calls = Rc::new(Cell::new(0));
+    /// let counter = calls.clone();
+    /// let handle = x.register_hook(move |grad| {
+    ///     counter.set(counter.get() + 1);
+    ///     Some(grad.get_data().clip(-1., 1.).into())
+    /// });
+    ///
+    /// (x.clone() * x.clone()).backward();
+    /// assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 2.);
+    /// assert_eq!(calls.get(), 2);
+    ///
+    /// handle.remove();
+    /// x.clear_grad();
+    /// (x.clone() * x.clone()).backward();
+    /// assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 4.);
+    /// ```
+    pub fn register_hook<F>(&self, hook: F) -> HookHandle<T>
+    where
+        F: Fn(&Variable<T>) -> Option<Variable<T>> + 'static,
+    {
+        let mut inner = self.inner.borrow_mut();
+        let id = inner.next_hook_id;
+        inner.next_hook_id += 1;
+        inner.hooks.push((id, Rc::new(hook)));
+        HookHandle {
+            variable: self.clone().downgrade(),
+            id,
+        }
+    }
+
+    pub(crate) fn apply_hooks(&self, grad: Variable<T>) -> Variable<T> {
+        // フックの中から変数に触れられるよう、借用を外してから呼ぶ
+        let hooks = self
+            .inner
+            .borrow()
+            .hooks
+            .iter()
+            .map(|(_, hook)| hook.clone())
+            .collect::<Vec<_>>();
+        hooks
+            .iter()
+            .fold(grad, |grad, hook| hook(&grad).unwrap_or(grad))
+    }
+}
+
+#[cfg(test)]
+mod hook {
+    use std::{cell::RefCell, rc::Rc};
+
+    use zenu_matrix::{
+        matrix::{IndexItem, ToViewMatrix},
+        operation::asum::Asum,
+    };
+
+    use crate::creator::from_vec::from_vec;
+
+    #[test]
+    fn hooks_chain_in_order() {
+        let x = from_vec(vec![1., -2.], [2]);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        x.register_hook(move |grad| {
+            log.borrow_mut().push(grad.get_data().to_view().asum());
+            Some(grad.clone() * from_vec(vec![10., 10.], [2]))
+        });
+        let log = seen.clone();
+        x.register_hook(move |grad| {
+            log.borrow_mut().push(grad.get_data().to_view().asum());
+            None
+        });
+
+        let y = x.clone() * from_vec(vec![3., 3.], [2]);
+        y.backward();
+        assert_eq!(*seen.borrow(), vec![6., 60.]);
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([1]), 30.);
+    }
+
+    #[test]
+    fn remove_only_its_own_hook() {
+        let x = from_vec(vec![1.], [1]);
+        let first = Rc::new(RefCell::new(0));
+        let second = Rc::new(RefCell::new(0));
+        let (f, s) = (first.clone(), second.clone());
+        let handle = x.register_hook(move |_| {
+            *f.borrow_mut() += 1;
+            None
+        });
+        x.register_hook(move |_| {
+            *s.borrow_mut() += 1;
+            None
+        });
+        handle.remove();
+
+        (x.clone() * x.clone()).backward();
+        assert_eq!(*first.borrow(), 0);
+        assert_eq!(*second.borrow(), 2);
+    }
+
+    #[test]
+    fn hook_on_intermediate_variable() {
+        let x = from_vec(vec![2.], [1]);
+        let y = x.clone() * x.clone();
+        // yより先へ流れる勾配を止める
+        y.register_hook(|grad| Some(grad.clone() * from_vec(vec![0.], [1])));
+        let z = y.clone() * from_vec(vec![5.], [1]);
+        z.backward();
+        assert_eq!(y.get_grad().unwrap().get_data().index_item([0]), 0.);
+        assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "shape of grad and data must be same")]
+    fn hook_returning_wrong_shape_panics() {
+        let x = from_vec(vec![1., 2.], [2]);
+        x.register_hook(|_| Some(from_vec(vec![1., 2., 3.], [3])));
+        (x.clone() * x.clone()).backward();
+    }
+}
@@ -1,17 +1,21 @@
 pub mod concat;
 pub mod creator;
+mod dot;
 pub mod functions;
+mod grad;
+pub mod gradcheck;
+mod hook;
+pub mod mode;
 
 use std::{
     cell::{Ref, RefCell, RefMut},
-    collections::{BinaryHeap, HashSet},
+    collections::{BinaryHeap, HashMap, HashSet},
     fmt::{Debug, Display},
     ops::Deref,
     rc::{Rc, Weak},
-    sync::Mutex,
 };
 
-use lazy_static::lazy_static;
+use hook::GradHook;
 use zenu_matrix::{
     constructor::ones::Ones,
     dim::DimDyn,
@@ -25,30 +29,25 @@ pub trait Function<T: Num> {
     fn forward(&self);
     fn backward(&self);
     fn get_inputs(&self) -> Vec<Variable<T>>;
+    /// Name of the operation, used when the graph is exported. Defaults to the name of the
+    /// implementing type without its module path and generic parameters.
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
     fn get_gen(&self) -> usize {
         let inputs = self.get_inputs();
         inputs.iter().map(|input| input.get_gen()).max().unwrap()
     }
 }
 
-lazy_static! {
-    static ref IS_TRAIN: Mutex<bool> = Mutex::new(true);
-}
-
-pub fn no_train() {
-    let mut is_train = IS_TRAIN.lock().unwrap();
-    *is_train = false;
-}
-
-pub fn is_train() -> bool {
-    let is_train = IS_TRAIN.lock().unwrap();
-    *is_train
-}
-
-pub fn set_train() {
-    let mut is_train = IS_TRAIN.lock().unwrap();
-    *is_train = true;
-}
+pub use grad::{grad, grad_with};
+pub use hook::HookHandle;
+pub use mode::{
+    eval_mode, is_grad_enabled, is_train, no_grad, no_train, set_grad_enabled, set_train,
+    train_mode, NoGradGuard, TrainModeGuard,
+};
 
 #[derive(Clone)]
 pub(crate) struct FunctionQueueItem<T: Num> {
@@ -95,6 +94,74 @@ impl<T: Num> Deref for FunctionQueueItem<T> {
     }
 }
 
+/// Runs the backward pass of every function reachable from `roots`, latest generation first,
+/// so each function sees the fully accumulated gradient of its output.
+///
+/// Without `retain_graph` the creator links of a function's outputs are dropped as soon as its
+/// backward has run, which frees the function together with the inputs it saved once nothing
+/// else refers to them. Every function of the graph is popped only after all consumers of its
+/// outputs, so all of its reachable outputs are known by then.
+///
+/// Every variable reached is checked against an earlier pass without `retain_graph`: one whose
+/// creator was released there would otherwise look like a leaf and silently get no further
+/// gradient.
+pub(crate) fn run_backward<T: Num>(roots: &[Variable<T>], retain_graph: bool) {
+    let starts = roots
+        .iter()
+        .filter_map(|root| {
+            root.assert_graph_alive();
+            root.get_creator()
+                .map(|creator| (creator, Some(root.clone())))
+        })
+        .collect();
+    run_backward_from(starts, retain_graph);
+}
+
+/// Runs backward from the given creators, each with the root variable it produced. A root is
+/// only needed to release its creator when the graph is not retained.
+#[allow(clippy::type_complexity)]
+fn run_backward_from<T: Num>(
+    starts: Vec<(Rc<RefCell<Box<dyn Function<T>>>>, Option<Variable<T>>)>,
+    retain_graph: bool,
+) {
+    let mut funcs: BinaryHeap<FunctionQueueItem<T>> = BinaryHeap::new();
+    let mut seen_rc = HashSet::new();
+    let mut outputs: HashMap<_, Vec<Variable<T>>> = HashMap::new();
+
+    for (creator, root) in starts {
+        if let (false, Some(root)) = (retain_graph, root) {
+            outputs.entry(creator.as_ptr()).or_default().push(root);
+        }
+        if seen_rc.insert(creator.as_ptr()) {
+            funcs.push(creator.into());
+        }
+    }
+
+    while let Some(FunctionQueueItem { func, .. }) = funcs.pop() {
+        func.borrow().backward();
+        func.borrow().get_inputs().iter().for_each(|input| {
+            // 以前のbackwardで生成元を解放された変数は葉と区別がつかないので、ここで止める
+            input.assert_graph_alive();
+            if let Some(creator) = input.get_creator() {
+                if !retain_graph {
+                    outputs
+                        .entry(creator.as_ptr())
+                        .or_default()
+                        .push(input.clone());
+                }
+                if seen_rc.insert(creator.as_ptr()) {
+                    funcs.push(creator.clone().into());
+                }
+            }
+        });
+        if !retain_graph {
+            for output in outputs.remove(&func.as_ptr()).unwrap_or_default() {
+                output.release_creator();
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct VariableInner<T: Num> {
     data: Matrix<OwnedMem<T>, DimDyn>,
@@ -103,6 +170,9 @@ pub struct VariableInner<T: Num> {
     gen: usize,
     name: Option<String>,
     is_train: bool,
+    graph_freed: bool,
+    hooks: Vec<(usize, GradHook<T>)>,
+    next_hook_id: usize,
 }
 
 impl<T: Num> VariableInner<T> {
@@ -114,6 +184,9 @@ impl<T: Num> VariableInner<T> {
             gen: 0,
             name: None,
             is_train: false,
+            graph_freed: false,
+            hooks: Vec::new(),
+            next_hook_id: 0,
         }
     }
 
@@ -122,6 +195,15 @@ impl<T: Num> VariableInner<T> {
         self.creator.clone()
     }
 
+    /// Runs backward from the creator of this variable, whose gradient must already be set.
+    /// Like [`Variable::backward`] the backward pass is recorded and the graph is kept.
+    #[deprecated(note = "use `Variable::backward` or `Variable::backward_with`")]
+    pub fn backward(&self) {
+        let creator = self.creator.clone().unwrap();
+        let _guard = set_grad_enabled(true);
+        run_backward_from(vec![(creator, None)], true);
+    }
+
     fn set_creator(&mut self, creator: Rc<RefCell<Box<dyn Function<T>>>>) {
         self.creator = Some(creator);
         let gen = self.creator.as_ref().unwrap().borrow().get_gen();
@@ -140,25 +222,6 @@ impl<T: Num> VariableInner<T> {
         self.name = Some(name);
     }
 
-    pub fn backward(&self) {
-        let mut funcs: BinaryHeap<FunctionQueueItem<T>> = BinaryHeap::new();
-        let mut seen_rc = HashSet::new();
-
-        funcs.push(self.creator.clone().unwrap().into());
-
-        while let Some(FunctionQueueItem { func, .. }) = funcs.pop() {
-            func.borrow().backward();
-            func.borrow().get_inputs().iter().for_each(|input| {
-                if let Some(creator) = input.get_creator() {
-                    if !seen_rc.contains(&creator.as_ptr()) {
-                        funcs.push(creator.clone().into());
-                        seen_rc.insert(creator.as_ptr());
-                    }
-                }
-            });
-        }
-    }
-
     fn clear_grad(&mut self) {
         if let Some(ref mut grad) = self.grad {
             grad.inner.borrow_mut().clear_grad();
@@ -261,12 +324,23 @@ impl<T: Num> Variable<T> {
         ref_v.clone()
     }
 
+    /// A new leaf holding a copy of the data, cut off from the graph: it has no creator, no
+    /// gradient and is not trainable, so nothing flows back into `self` through it.
+    pub fn detach(&self) -> Variable<T> {
+        Variable::new(self.get_data())
+    }
+
     pub fn get_data_mut<'a>(&'a self) -> RefMut<'a, Matrix<OwnedMem<T>, DimDyn>> {
         let reference: RefMut<'a, VariableInner<T>> = self.inner.borrow_mut();
         RefMut::map(reference, |r| &mut r.data)
     }
 
+    /// Attaches `creator` to this variable. Does nothing while a [`no_grad`] guard is alive,
+    /// so outputs computed in that scope are leaves.
     pub fn set_creator(&self, creator: Rc<RefCell<Box<dyn Function<T>>>>) {
+        if !is_grad_enabled() {
+            return;
+        }
         self.inner.borrow_mut().set_creator(creator);
     }
 
@@ -285,14 +359,57 @@ impl<T: Num> Variable<T> {
         RefMut::map(reference, |r| &mut r.grad)
     }
 
+    /// Back-propagates from this variable with the backward pass recorded and the graph kept,
+    /// as it always has: shorthand for [`Variable::backward_with`]`(true, true)`. Use
+    /// `backward_with(false, _)` when the gradients will not be differentiated again, so no
+    /// graph is built for them.
     pub fn backward(&self) {
+        self.backward_with(true, true);
+    }
+
+    /// Back-propagates from this variable, accumulating into `.grad` of every variable in the
+    /// graph.
+    ///
+    /// With `create_graph` the backward pass is itself recorded, so the resulting gradients have
+    /// creators and can be differentiated again.
+    ///
+    /// Without `retain_graph` the graph is freed while it is traversed: every function is
+    /// dropped right after its backward, together with the inputs it saved, so peak memory
+    /// stays close to that of the forward pass. Only the leaves and their gradients are left,
+    /// and calling backward through the freed part again panics.
+    pub fn backward_with(&self, create_graph: bool, retain_graph: bool) {
+        self.assert_graph_alive();
+        self.seed_grad();
+        let _guard = set_grad_enabled(create_graph);
+        run_backward(std::slice::from_ref(self), retain_graph);
+    }
+
+    fn assert_graph_alive(&self) {
+        if self.inner.borrow().graph_freed {
+            panic!(
+                "the graph of {} was already freed by a backward pass with retain_graph = false; \
+                 use backward_with(_, true) to run backward through the same graph more than once",
+                self.get_name()
+                    .map_or_else(|| "this variable".to_string(), |name| format!("`{name}`"))
+            );
+        }
+    }
+
+    /// Unlinks this variable from the function that created it.
+    fn release_creator(&self) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.creator.take().is_some() {
+            inner.graph_freed = true;
+        }
+    }
+
+    fn seed_grad(&self) {
         if self.inner.borrow().grad.is_none() {
             let ones = Ones::ones(self.get_data().shape());
             let ones = Variable::new(ones);
             ones.set_name(&format!("{:?}_grad", self.get_name().unwrap_or_default()));
             self.inner.borrow_mut().grad = Some(ones);
         }
-        self.inner.borrow().backward();
     }
 
     pub fn downgrade(self) -> VariableWeak<T> {
@@ -307,6 +424,10 @@ impl<T: Num> Variable<T> {
 
     pub fn clear_grad(&self) {
         self.inner.borrow_mut().clear_grad();
+        // 葉や、backwardで解放済みのグラフには辿る先がない
+        if self.get_creator().is_none() {
+            return;
+        }
         let all_val = self.inner.borrow().get_all_variable();
         for val in all_val {
             val.inner.borrow_mut().clear_grad();
@@ -335,6 +456,8 @@ impl<T: Num> Variable<T> {
     }
 
     pub fn set_grad(&self, grad: Variable<T>) {
+        // hookが返した値も含めて形を確かめる
+        let grad = self.apply_hooks(grad);
         if self.get_data().shape() != grad.get_data().shape() {
             panic!("shape of grad and data must be same");
         }
@@ -0,0 +1,177 @@
+//! Thread-local autograd modes.
+//!
+//! Two independent switches live here:
+//!
+//! * gradient recording — while a [`NoGradGuard`] is alive, functions still compute their
+//!   outputs but no creator is attached, so no graph is built and nothing can be
+//!   back-propagated through the results;
+//! * train / eval mode — read by layers such as batch norm and dropout.
+//!
+//! Both are stored per thread and every guard restores the previous value when dropped, so
+//! guards can be nested and early returns or panics cannot leave the mode switched.
+//!
+//! ```
+//! use zenu_autograd::{is_grad_enabled, is_train, eval_mode, no_grad};
+//!
+//! {
+//!     let _no_grad = no_grad();
+//!     let _eval = eval_mode();
+//!     assert!(!is_grad_enabled());
+//!     assert!(!is_train());
+//! }
+//! assert!(is_grad_enabled());
+//! assert!(is_train());
+//! ```
+use std::{cell::Cell, marker::PhantomData};
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+    static IS_TRAIN: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Restores the previous gradient recording state on drop.
+#[must_use = "gradient recording is restored as soon as the guard is dropped"]
+pub struct NoGradGuard {
+    prev: bool,
+    // スレッドローカルな状態を戻すので別スレッドに送れないようにする
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.prev));
+    }
+}
+
+/// Restores the previous train / eval mode on drop.
+#[must_use = "the mode is restored as soon as the guard is dropped"]
+pub struct TrainModeGuard {
+    prev: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for TrainModeGuard {
+    fn drop(&mut self) {
+        IS_TRAIN.with(|is_train| is_train.set(self.prev));
+    }
+}
+
+/// Disables graph construction on the current thread until the guard is dropped.
+pub fn no_grad() -> NoGradGuard {
+    set_grad_enabled(false)
+}
+
+/// Enables or disables graph construction on the current thread until the guard is dropped.
+pub fn set_grad_enabled(enabled: bool) -> NoGradGuard {
+    let prev = GRAD_ENABLED.with(|cell| cell.replace(enabled));
+    NoGradGuard {
+        prev,
+        _not_send: PhantomData,
+    }
+}
+
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+/// Switches the current thread to train mode until the guard is dropped.
+pub fn train_mode() -> TrainModeGuard {
+    set_train_mode(true)
+}
+
+/// Switches the current thread to eval mode until the guard is dropped.
+pub fn eval_mode() -> TrainModeGuard {
+    set_train_mode(false)
+}
+
+fn set_train_mode(is_train: bool) -> TrainModeGuard {
+    let prev = IS_TRAIN.with(|cell| cell.replace(is_train));
+    TrainModeGuard {
+        prev,
+        _not_send: PhantomData,
+    }
+}
+
+pub fn is_train() -> bool {
+    IS_TRAIN.with(|is_train| is_train.get())
+}
+
+/// Switches the current thread to eval mode without a guard. Prefer [`eval_mode`].
+pub fn no_train() {
+    IS_TRAIN.with(|is_train| is_train.set(false));
+}
+
+/// Switches the current thread to train mode without a guard. Prefer [`train_mode`].
+pub fn set_train() {
+    IS_TRAIN.with(|is_train| is_train.set(true));
+}
+
+#[cfg(test)]
+mod mode {
+    use zenu_matrix::{
+        matrix::{OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::asum::Asum,
+    };
+
+    use crate::Variable;
+
+    use super::{eval_mode, is_grad_enabled, is_train, no_grad, set_grad_enabled, train_mode};
+
+    #[test]
+    fn no_grad_nested() {
+        assert!(is_grad_enabled());
+        {
+            let _outer = no_grad();
+            assert!(!is_grad_enabled());
+            {
+                let _inner = set_grad_enabled(true);
+                assert!(is_grad_enabled());
+            }
+            assert!(!is_grad_enabled());
+        }
+        assert!(is_grad_enabled());
+    }
+
+    #[test]
+    fn no_grad_skips_graph() {
+        let x = Variable::new(OwnedMatrixDyn::from_vec(vec![1., 2., 3.], [3]));
+        let y = {
+            let _guard = no_grad();
+            x.clone() * x.clone()
+        };
+        assert!(y.get_creator().is_none());
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 4., 9.], [3]);
+        assert!((y.get_data().to_view() - ans.to_view()).asum() < 1e-6);
+
+        let z = x.clone() * x.clone();
+        assert!(z.get_creator().is_some());
+    }
+
+    #[test]
+    fn train_mode_nested() {
+        assert!(is_train());
+        {
+            let _eval = eval_mode();
+            assert!(!is_train());
+            {
+                let _train = train_mode();
+                assert!(is_train());
+            }
+            assert!(!is_train());
+        }
+        assert!(is_train());
+    }
+
+    #[test]
+    fn modes_are_thread_local() {
+        let _guard = no_grad();
+        let _eval = eval_mode();
+        std::thread::spawn(|| {
+            assert!(is_grad_enabled());
+            assert!(is_train());
+        })
+        .join()
+        .unwrap();
+    }
+}
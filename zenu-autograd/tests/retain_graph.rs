@@ -0,0 +1,49 @@
+use zenu_autograd::{
+    creator::from_vec::from_vec,
+    functions::{exp::exp, tanh::tanh},
+};
+use zenu_matrix::matrix::IndexItem;
+
+#[test]
+fn release_intermediate_variables() {
+    let x = from_vec(vec![1., 2.], [2]);
+    let y = x.clone() * x.clone();
+    let weak_y = y.clone().downgrade();
+    let z = exp(y);
+
+    z.backward_with(false, false);
+
+    // exp(x^2)' = 2x exp(x^2)
+    let gx = x.get_grad().unwrap().get_data();
+    assert!((gx.index_item([0]) - 2. * 1f64.exp()).abs() < 1e-9);
+    assert!((gx.index_item([1]) - 4. * 4f64.exp()).abs() < 1e-9);
+    assert!(weak_y.upgrade().is_none());
+    assert!(z.get_creator().is_none());
+}
+
+#[test]
+#[should_panic(expected = "already freed")]
+fn second_backward_panics() {
+    let x = from_vec(vec![1., 2.], [2]);
+    let y = exp(x.clone() * x);
+    y.backward_with(false, false);
+    y.backward();
+}
+
+#[test]
+fn retain_graph_allows_second_backward() {
+    let x = from_vec(vec![3.], [1]);
+    let y = x.clone() * x.clone();
+    y.backward_with(false, true);
+    y.backward_with(false, false);
+    assert_eq!(x.get_grad().unwrap().get_data().index_item([0]), 12.);
+}
+
+#[test]
+#[should_panic(expected = "already freed")]
+fn backward_through_freed_shared_subgraph_panics() {
+    let x = from_vec(vec![1., 2.], [2]);
+    let y = x.clone() * x.clone();
+    exp(y.clone()).backward_with(false, false);
+    tanh(y).backward();
+}
@@ -260,4 +260,30 @@ mod tests {
         assert!(diff_bias_asum < 1e-6);
         assert!(diff_weight_asum < 1e-6);
     }
+
+    #[test]
+    fn grad_hook_on_all_parameters() {
+        let weight = from_vec(vec![1., 2., 3., 4., 5., 6.], [3, 2]);
+        let bias = from_vec(vec![1., 2.], [2]);
+        let mut linear_layer = Linear::new(3, 2);
+        linear_layer.load_parameters(&[weight.clone(), bias.clone()]);
+        let handles = linear_layer.register_grad_hook(|grad| Some(grad.clone() + grad.clone()));
+        assert_eq!(handles.len(), 2);
+
+        let y = linear_layer.call(from_vec(vec![1., 2., 3.], [1, 3]));
+        y.backward();
+        let bias_ans = OwnedMatrixDyn::from_vec(vec![2., 2.], [2]);
+        let weight_ans = OwnedMatrixDyn::from_vec(vec![2., 2., 4., 4., 6., 6.], [3, 2]);
+        assert!((bias.get_grad().unwrap().get_data() - bias_ans).asum() < 1e-6);
+        assert!((weight.get_grad().unwrap().get_data() - weight_ans).asum() < 1e-6);
+
+        for handle in handles {
+            handle.remove();
+        }
+        linear_layer.clear_gradients();
+        let y = linear_layer.call(from_vec(vec![1., 2., 3.], [1, 3]));
+        y.backward();
+        let bias_ans = OwnedMatrixDyn::from_vec(vec![1., 1.], [2]);
+        assert!((bias.get_grad().unwrap().get_data() - bias_ans).asum() < 1e-6);
+    }
 }
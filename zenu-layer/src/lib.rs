@@ -1,6 +1,6 @@
 use rand::distributions::Distribution;
 use rand_distr::StandardNormal;
-use zenu_autograd::Variable;
+use zenu_autograd::{HookHandle, Variable};
 use zenu_matrix::num::Num;
 
 pub mod layers;
@@ -16,6 +16,17 @@ pub trait Layer<T: Num> {
             parameter.clear_grad();
         }
     }
+    /// Registers `hook` on the gradient of every parameter. See [`Variable::register_hook`].
+    fn register_grad_hook<F>(&self, hook: F) -> Vec<HookHandle<T>>
+    where
+        F: Fn(&Variable<T>) -> Option<Variable<T>> + Clone + 'static,
+        Self: Sized,
+    {
+        self.parameters()
+            .iter()
+            .map(|parameter| parameter.register_hook(hook.clone()))
+            .collect()
+    }
     fn call(&self, input: Variable<T>) -> Variable<T>;
     fn shape_check(&self, input: &Variable<T>);
 }
@@ -15,9 +15,6 @@ where
     OM: OwnedMatrix + MatrixBase<Dim = D, Item = T>,
 {
     fn ones<I: Into<D>>(dim: I) -> Self {
-        let dim = dim.into();
-        let data = vec![T::one(); dim.num_elm()];
-        let vec = data.iter().map(|_| T::from_usize(1)).collect();
-        Self::from_vec(vec, dim)
+        Self::from_fn(dim, |_| T::one())
     }
 }
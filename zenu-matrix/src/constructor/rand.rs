@@ -36,11 +36,7 @@ where
         Box::new(thread_rng())
     };
     let normal = Normal::new(mean, std_dev).unwrap();
-    let mut data = Vec::with_capacity(shape.num_elm());
-    for _ in 0..shape.num_elm() {
-        data.push(normal.sample(&mut *rng));
-    }
-    Matrix::from_vec(data, shape)
+    Matrix::from_fn(shape, |_| normal.sample(&mut *rng))
 }
 
 /// Creates a matrix filled with random values from a normal distribution with the same shape as another matrix.
@@ -87,11 +83,7 @@ where
         Box::new(thread_rng())
     };
     let uniform = Uniform::new(low, high);
-    let mut data = Vec::with_capacity(shape.num_elm());
-    for _ in 0..shape.num_elm() {
-        data.push(uniform.sample(&mut *rng));
-    }
-    Matrix::from_vec(data, shape)
+    Matrix::from_fn(shape, |_| uniform.sample(&mut *rng))
 }
 
 /// Creates a matrix filled with random values from a uniform distribution with the same shape as another matrix.
@@ -15,10 +15,7 @@ where
     OM: OwnedMatrix + MatrixBase<Dim = D, Item = T>,
 {
     fn zeros<I: Into<Self::Dim>>(dim: I) -> Self {
-        let dim = dim.into();
-        let num_elm = dim.num_elm();
-        let data = vec![T::zero(); num_elm];
-        <Self as OwnedMatrix>::from_vec(data, dim)
+        <Self as OwnedMatrix>::from_fn(dim, |_| T::zero())
     }
 
     fn zeros_like<M: MatrixBase>(m: M) -> Self {
@@ -0,0 +1,274 @@
+//! Size-bucketed caching allocator for CPU matrix memory.
+//!
+//! [`OwnedMem`](crate::memory_impl::OwnedMem) buffers built in place on the CPU, such as
+//! zeros, ones, random values and clones, get their memory from here. A `Vec` handed to
+//! `from_vec` is adopted as is and goes back to the system allocator when dropped.
+//! Requests are rounded up to a bucket size and freed buffers are kept in a per-bucket
+//! free list instead of being returned to the system, so the many temporaries created
+//! in a training loop reuse the same few buffers.
+//!
+//! Buckets are multiples of 512 bytes below 1 MiB and multiples of 2 MiB above.
+//! Cached buffers are only returned to the system by [`empty_cache`].
+//!
+//! ```
+//! use zenu_matrix::{
+//!     constructor::{ones::Ones, zeros::Zeros},
+//!     cpu_allocator,
+//!     matrix::AsPtr,
+//!     matrix_impl::OwnedMatrixDyn,
+//! };
+//!
+//! let x: OwnedMatrixDyn<f32> = Zeros::zeros([256, 256]);
+//! let ptr = x.as_ptr();
+//! drop(x);
+//! // the freed buffer is handed to the next matrix of the same size
+//! let y: OwnedMatrixDyn<f32> = Ones::ones([256, 256]);
+//! assert_eq!(y.as_ptr(), ptr);
+//! drop(y);
+//!
+//! let stats = cpu_allocator::stats();
+//! assert!(stats.peak_bytes_in_use >= 256 * 256 * 4);
+//! cpu_allocator::empty_cache();
+//! ```
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    collections::HashMap,
+    ptr::NonNull,
+    sync::{Mutex, OnceLock},
+};
+
+const MIN_BLOCK_SIZE: usize = 512;
+const LARGE_BLOCK_THRESHOLD: usize = 1 << 20;
+const LARGE_BLOCK_ROUND: usize = 2 << 20;
+
+/// Snapshot of the allocator counters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// Bytes of buffers currently owned by matrices, counted with bucket rounding.
+    pub bytes_in_use: usize,
+    /// Largest value `bytes_in_use` has reached since the last [`reset_peak_stats`].
+    pub peak_bytes_in_use: usize,
+    /// Bytes of freed buffers kept for reuse.
+    pub bytes_cached: usize,
+    /// Allocations served from the cache.
+    pub cache_hits: usize,
+    /// Allocations that had to go to the system allocator.
+    pub cache_misses: usize,
+}
+
+/// メモリブロックのアドレス。`*mut u8`はSendではないのでusizeで持つ
+type Addr = usize;
+
+#[derive(Default)]
+pub(crate) struct CachingAllocator {
+    /// (bucketのbyte数, align) -> 再利用できるブロック
+    free_blocks: HashMap<(usize, usize), Vec<Addr>>,
+    /// 使用中のブロックのLayout
+    live_blocks: HashMap<Addr, Layout>,
+    stats: AllocatorStats,
+}
+
+impl CachingAllocator {
+    pub(crate) fn allocate(&mut self, size: usize, align: usize) -> NonNull<u8> {
+        let bucket = bucket_size(size);
+        let layout = Layout::from_size_align(bucket, align).unwrap();
+
+        let cached = self
+            .free_blocks
+            .get_mut(&(bucket, align))
+            .and_then(|blocks| blocks.pop());
+        let addr = match cached {
+            Some(addr) => {
+                self.stats.cache_hits += 1;
+                self.stats.bytes_cached -= bucket;
+                addr
+            }
+            None => {
+                self.stats.cache_misses += 1;
+                let ptr = unsafe { alloc(layout) };
+                if ptr.is_null() {
+                    handle_alloc_error(layout);
+                }
+                ptr as Addr
+            }
+        };
+
+        self.live_blocks.insert(addr, layout);
+        self.stats.bytes_in_use += bucket;
+        self.stats.peak_bytes_in_use = self.stats.peak_bytes_in_use.max(self.stats.bytes_in_use);
+        NonNull::new(addr as *mut u8).unwrap()
+    }
+
+    /// `ptr`がこのallocatorのブロックならcacheに戻してtrueを返す
+    pub(crate) fn deallocate(&mut self, ptr: *const u8) -> bool {
+        let addr = ptr as Addr;
+        let Some(layout) = self.live_blocks.remove(&addr) else {
+            return false;
+        };
+        self.stats.bytes_in_use -= layout.size();
+        self.stats.bytes_cached += layout.size();
+        self.free_blocks
+            .entry((layout.size(), layout.align()))
+            .or_default()
+            .push(addr);
+        true
+    }
+
+    pub(crate) fn empty_cache(&mut self) {
+        for ((size, align), blocks) in self.free_blocks.drain() {
+            let layout = Layout::from_size_align(size, align).unwrap();
+            for addr in blocks {
+                unsafe { dealloc(addr as *mut u8, layout) };
+            }
+        }
+        self.stats.bytes_cached = 0;
+    }
+
+    pub(crate) fn stats(&self) -> AllocatorStats {
+        self.stats
+    }
+
+    pub(crate) fn reset_peak_stats(&mut self) {
+        self.stats.peak_bytes_in_use = self.stats.bytes_in_use;
+    }
+}
+
+impl Drop for CachingAllocator {
+    fn drop(&mut self) {
+        self.empty_cache();
+    }
+}
+
+fn bucket_size(size: usize) -> usize {
+    let round = if size < LARGE_BLOCK_THRESHOLD {
+        MIN_BLOCK_SIZE
+    } else {
+        LARGE_BLOCK_ROUND
+    };
+    size.max(1).div_ceil(round) * round
+}
+
+fn global() -> &'static Mutex<CachingAllocator> {
+    static ALLOCATOR: OnceLock<Mutex<CachingAllocator>> = OnceLock::new();
+    ALLOCATOR.get_or_init(|| Mutex::new(CachingAllocator::default()))
+}
+
+/// Allocates an uninitialized buffer for `len` values of `T`.
+pub(crate) fn allocate<T>(len: usize) -> NonNull<T> {
+    let size = std::mem::size_of::<T>() * len;
+    if size == 0 {
+        return NonNull::dangling();
+    }
+    global()
+        .lock()
+        .unwrap()
+        .allocate(size, std::mem::align_of::<T>())
+        .cast()
+}
+
+/// Returns a buffer to the cache.
+///
+/// Buffers that did not come from [`allocate`] are freed as a `Vec<T>` of capacity `len`.
+pub(crate) fn deallocate<T>(ptr: *const T, len: usize) {
+    if std::mem::size_of::<T>() * len == 0 {
+        return;
+    }
+    let cached = global().lock().unwrap().deallocate(ptr as *const u8);
+    if !cached {
+        let _ = unsafe { Vec::from_raw_parts(ptr as *mut T, len, len) };
+    }
+}
+
+/// Returns the current allocator counters.
+pub fn stats() -> AllocatorStats {
+    global().lock().unwrap().stats()
+}
+
+/// Resets `peak_bytes_in_use` to the current `bytes_in_use`.
+pub fn reset_peak_stats() {
+    global().lock().unwrap().reset_peak_stats();
+}
+
+/// Returns every cached buffer to the system allocator.
+pub fn empty_cache() {
+    global().lock().unwrap().empty_cache();
+}
+
+#[cfg(test)]
+mod cpu_allocator {
+    use super::{bucket_size, CachingAllocator};
+
+    #[test]
+    fn bucket_rounding() {
+        assert_eq!(bucket_size(1), 512);
+        assert_eq!(bucket_size(512), 512);
+        assert_eq!(bucket_size(513), 1024);
+        assert_eq!(bucket_size((1 << 20) + 1), 2 << 20);
+        assert_eq!(bucket_size(5 << 20), 6 << 20);
+    }
+
+    #[test]
+    fn reuse_cached_block() {
+        let mut allocator = CachingAllocator::default();
+        let a = allocator.allocate(1000, 8);
+        assert!(allocator.deallocate(a.as_ptr()));
+        // 1000 and 900 bytes share the 1024 byte bucket
+        let b = allocator.allocate(900, 8);
+        assert_eq!(a, b);
+
+        let stats = allocator.stats();
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(stats.cache_misses, 1);
+        assert_eq!(stats.bytes_in_use, 1024);
+        assert_eq!(stats.bytes_cached, 0);
+        allocator.deallocate(b.as_ptr());
+    }
+
+    #[test]
+    fn stats_and_empty_cache() {
+        let mut allocator = CachingAllocator::default();
+        let a = allocator.allocate(2000, 4);
+        let b = allocator.allocate(600, 4);
+        assert_eq!(allocator.stats().bytes_in_use, 2048 + 1024);
+        allocator.deallocate(a.as_ptr());
+        allocator.deallocate(b.as_ptr());
+
+        let stats = allocator.stats();
+        assert_eq!(stats.bytes_in_use, 0);
+        assert_eq!(stats.peak_bytes_in_use, 2048 + 1024);
+        assert_eq!(stats.bytes_cached, 2048 + 1024);
+
+        allocator.empty_cache();
+        assert_eq!(allocator.stats().bytes_cached, 0);
+        allocator.reset_peak_stats();
+        assert_eq!(allocator.stats().peak_bytes_in_use, 0);
+
+        // different alignments never share a block
+        let c = allocator.allocate(100, 4);
+        allocator.deallocate(c.as_ptr());
+        let d = allocator.allocate(100, 8);
+        assert_eq!(allocator.stats().cache_hits, 0);
+        allocator.deallocate(d.as_ptr());
+    }
+
+    #[test]
+    fn dropped_matrix_buffer_is_reused() {
+        // グローバルなallocatorは並列に走る他のテストと共有されるので、ローカルに作って確かめる
+        let mut allocator = CachingAllocator::default();
+        let size = std::mem::size_of::<f32>() * 123_457;
+        let align = std::mem::align_of::<f32>();
+        let a = allocator.allocate(size, align);
+        assert!(allocator.deallocate(a.as_ptr()));
+        let b = allocator.allocate(size, align);
+        assert_eq!(a, b);
+        assert_eq!(allocator.stats().cache_hits, 1);
+        allocator.deallocate(b.as_ptr());
+    }
+
+    #[test]
+    fn foreign_pointer_is_not_cached() {
+        let mut allocator = CachingAllocator::default();
+        let v = [0u8; 4];
+        assert!(!allocator.deallocate(v.as_ptr()));
+    }
+}
@@ -1,6 +1,7 @@
 pub mod blas;
 pub mod concat;
 pub mod constructor;
+pub mod cpu_allocator;
 pub mod cpu_blas;
 pub mod cpu_element_wise;
 pub mod dim;
@@ -12,10 +13,15 @@ pub mod matrix_impl;
 pub mod matrix_iter;
 pub mod memory;
 pub mod memory_impl;
+#[cfg(feature = "mmap")]
+pub mod memory_mmap;
+#[cfg(feature = "ndarray")]
+pub mod ndarray_interop;
 pub mod num;
 pub mod operation;
+pub mod quantize;
 pub mod shape_stride;
 pub mod slice;
 
 mod impl_ops;
-mod matrix_format;
+pub mod matrix_format;
@@ -152,4 +152,7 @@ pub trait ViewMutMatix:
 
 pub trait OwnedMatrix: MatrixBase + ToViewMatrix + ToViewMutMatrix + AsPtr + BlasMatrix {
     fn from_vec<I: Into<Self::Dim>>(vec: Vec<Self::Item>, dim: I) -> Self;
+    /// Builds a matrix of shape `dim` whose `i`-th element in row-major order is `f(i)`,
+    /// written straight into newly allocated memory.
+    fn from_fn<I: Into<Self::Dim>, F: FnMut(usize) -> Self::Item>(dim: I, f: F) -> Self;
 }
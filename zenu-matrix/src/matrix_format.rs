@@ -1,4 +1,32 @@
-use std::fmt;
+//! Pretty-printing for matrices.
+//!
+//! `Display` and `Debug` print the elements in a nested, numpy-like layout. Matrices
+//! with many elements are summarized with ellipses. The layout is controlled by
+//! [`PrintOptions`], either globally with [`set_print_options`] or for a single call
+//! with [`Matrix::display_with`]. Width and precision given in the format string
+//! (`{:8.3}`) are applied to every element and take priority over the options.
+//!
+//! ```
+//! use zenu_matrix::{
+//!     matrix::OwnedMatrix,
+//!     matrix_format::PrintOptions,
+//!     matrix_impl::OwnedMatrixDyn,
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec(vec![1.5, 2., 3., 4.], [2, 2]);
+//! assert_eq!(format!("{}", x), "[[1.5, 2],\n [3, 4]]");
+//!
+//! let options = PrintOptions {
+//!     precision: Some(2),
+//!     header: true,
+//!     ..Default::default()
+//! };
+//! assert_eq!(
+//!     x.display_with(options).to_string(),
+//!     "shape=[2, 2], stride=[2, 1], dtype=f64\n[[1.50, 2.00],\n [3.00, 4.00]]"
+//! );
+//! ```
+use std::{fmt, sync::RwLock};
 
 use crate::{
     dim::{DimDyn, DimTrait},
@@ -22,21 +50,97 @@ const AXIS_LIMIT_ROW: usize = 11;
 /// The string used as an ellipsis.
 const ELLIPSIS: &str = "...";
 
+/// Options for printing matrices, similar to numpy's `set_printoptions`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrintOptions {
+    /// Digits after the decimal point. `None` prints the shortest representation that
+    /// round-trips.
+    pub precision: Option<usize>,
+    /// Maximum number of characters per line; longer rows wrap. `None` never wraps.
+    pub linewidth: Option<usize>,
+    /// Matrices with at least this many elements are summarized with ellipses.
+    pub threshold: usize,
+    /// Number of items printed at the start and at the end of a summarized axis.
+    /// `None` prints 5 along the last two axes and 3 along the others.
+    pub edgeitems: Option<usize>,
+    /// Print every element in scientific notation, e.g. `1.5e2`.
+    pub scientific: bool,
+    /// Print a `shape=[..], stride=[..], dtype=..` line before the elements. `Debug` then
+    /// leaves out its usual `, shape=.., strides=..` suffix.
+    pub header: bool,
+}
+
+impl PrintOptions {
+    pub const fn new() -> Self {
+        Self {
+            precision: None,
+            linewidth: None,
+            threshold: ARRAY_MANY_ELEMENT_LIMIT,
+            edgeitems: None,
+            scientific: false,
+            header: false,
+        }
+    }
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static PRINT_OPTIONS: RwLock<PrintOptions> = RwLock::new(PrintOptions::new());
+
+/// Sets the options used by `Display` and `Debug` for every matrix.
+pub fn set_print_options(options: PrintOptions) {
+    *PRINT_OPTIONS.write().unwrap() = options;
+}
+
+/// Returns the current global print options.
+pub fn print_options() -> PrintOptions {
+    *PRINT_OPTIONS.read().unwrap()
+}
+
+/// Restores the default global print options.
+pub fn reset_print_options() {
+    set_print_options(PrintOptions::new());
+}
+
 #[derive(Clone, Debug)]
 struct FormatOptions {
     axis_collapse_limit: usize,
     axis_collapse_limit_next_last: usize,
     axis_collapse_limit_last: usize,
+    width: Option<usize>,
+    precision: Option<usize>,
+    scientific: bool,
+    debug: bool,
+    linewidth: usize,
 }
 
 impl FormatOptions {
-    pub(crate) fn default_for_array(nelem: usize, no_limit: bool) -> Self {
+    pub(crate) fn new(
+        nelem: usize,
+        f: &fmt::Formatter<'_>,
+        options: &PrintOptions,
+        debug: bool,
+    ) -> Self {
+        let (stacked, col, row) = match options.edgeitems {
+            // edgeitemsが0だと何も表示されなくなるので最低1つは表示する
+            Some(edge) => (edge.max(1) * 2, edge.max(1) * 2, edge.max(1) * 2),
+            None => (AXIS_LIMIT_STACKED, AXIS_LIMIT_COL, AXIS_LIMIT_ROW),
+        };
         let default = Self {
-            axis_collapse_limit: AXIS_LIMIT_STACKED,
-            axis_collapse_limit_next_last: AXIS_LIMIT_COL,
-            axis_collapse_limit_last: AXIS_LIMIT_ROW,
+            axis_collapse_limit: stacked,
+            axis_collapse_limit_next_last: col,
+            axis_collapse_limit_last: row,
+            width: f.width(),
+            precision: f.precision().or(options.precision),
+            scientific: options.scientific,
+            debug,
+            linewidth: options.linewidth.unwrap_or(usize::MAX),
         };
-        default.set_no_limit(no_limit || nelem < ARRAY_MANY_ELEMENT_LIMIT)
+        default.set_no_limit(f.alternate() || nelem < options.threshold)
     }
 
     fn set_no_limit(mut self, no_limit: bool) -> Self {
@@ -57,6 +161,45 @@ impl FormatOptions {
             _ => self.axis_collapse_limit,
         }
     }
+
+    /// Formats one element with the width, precision and notation of these options.
+    fn format_elem<T: Num>(&self, f: &mut fmt::Formatter<'_>, elem: T) -> fmt::Result {
+        let w = self.width.unwrap_or(0);
+        match (self.scientific, self.precision) {
+            (true, precision) => {
+                // `Num`は`LowerExp`を要求しないので、具体的な浮動小数点型に変換して書く
+                if T::is_f32() {
+                    write_scientific(f, elem.to_f32().unwrap(), w, precision)
+                } else {
+                    write_scientific(f, elem.to_f64().unwrap(), w, precision)
+                }
+            }
+            (false, Some(p)) => write!(f, "{:w$.p$}", elem),
+            (false, None) if self.debug => write!(f, "{:w$?}", elem),
+            (false, None) => write!(f, "{:w$}", elem),
+        }
+    }
+}
+
+fn write_scientific<T: fmt::LowerExp>(
+    f: &mut fmt::Formatter<'_>,
+    elem: T,
+    w: usize,
+    precision: Option<usize>,
+) -> fmt::Result {
+    match precision {
+        Some(p) => write!(f, "{:w$.p$e}", elem),
+        None => write!(f, "{:w$e}", elem),
+    }
+}
+
+/// `FormatOptions::format_elem`で要素を文字列にするためのwrapper
+struct Elem<'a, T>(T, &'a FormatOptions);
+
+impl<'a, T: Num> fmt::Display for Elem<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.1.format_elem(f, self.0)
+    }
 }
 
 /// Formats the contents of a list of items, using an ellipsis to indicate when
@@ -104,15 +247,40 @@ fn format_with_overflow(
     Ok(())
 }
 
-fn format_array<A, S, D, F>(
+/// Formats a row of already formatted items, wrapping onto a new line indented by
+/// `indent` spaces when the next item would go past `linewidth`.
+fn format_row_wrapped(
+    f: &mut fmt::Formatter<'_>,
+    items: &[String],
+    indent: usize,
+    linewidth: usize,
+) -> fmt::Result {
+    let mut column = indent;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            // ", "と次の要素、最後の"]"か","が収まるか
+            if column.saturating_add(item.len() + 3) > linewidth {
+                f.write_str(",\n")?;
+                f.write_str(&" ".repeat(indent))?;
+                column = indent;
+            } else {
+                f.write_str(", ")?;
+                column += 2;
+            }
+        }
+        f.write_str(item)?;
+        column += item.len();
+    }
+    Ok(())
+}
+
+fn format_array<A, S, D>(
     array: &Matrix<S, D>,
     f: &mut fmt::Formatter<'_>,
-    format: F,
     fmt_opt: &FormatOptions,
 ) -> fmt::Result
 where
     A: Num,
-    F: FnMut(&A, &mut fmt::Formatter<'_>) -> fmt::Result + Clone,
     D: DimTrait,
     S: Memory<Item = A> + ToViewMemory,
 {
@@ -121,17 +289,15 @@ where
     format_array_inner(
         array.to_view().into_dyn_dim(),
         f,
-        format,
         fmt_opt,
         0,
         array.shape().len(),
     )
 }
 
-fn format_array_inner<T, M, F>(
+fn format_array_inner<T, M>(
     view: Matrix<M, DimDyn>,
     f: &mut fmt::Formatter<'_>,
-    mut format: F,
     fmt_opt: &FormatOptions,
     depth: usize,
     full_ndim: usize,
@@ -139,23 +305,27 @@ fn format_array_inner<T, M, F>(
 where
     T: Num,
     M: Memory<Item = T> + ToViewMemory,
-    F: FnMut(&T, &mut fmt::Formatter<'_>) -> fmt::Result + Clone,
 {
     match view.shape().slice() {
         // If it's 0 dimensional, we just print out the scalar
-        &[] => format(&view.index_item(&[] as &[usize]), f)?,
+        &[] => fmt_opt.format_elem(f, view.index_item(&[] as &[usize]))?,
         // We handle 1-D arrays as a special case
         &[len] => {
             let view = view.into_dyn_dim();
+            let limit = fmt_opt.collapse_limit(0);
+            let elem = |index: usize| Elem(view.index_item([index]), fmt_opt).to_string();
+            let items = if len <= limit {
+                (0..len).map(elem).collect::<Vec<_>>()
+            } else {
+                let edge = limit / 2;
+                (0..edge)
+                    .map(elem)
+                    .chain(std::iter::once(ELLIPSIS.to_string()))
+                    .chain((len - edge..len).map(elem))
+                    .collect()
+            };
             f.write_str("[")?;
-            format_with_overflow(
-                f,
-                len,
-                fmt_opt.collapse_limit(0),
-                ", ",
-                ELLIPSIS,
-                &mut |f, index| format(&view.index_item([index]), f),
-            )?;
+            format_row_wrapped(f, &items, depth + 1, fmt_opt.linewidth)?;
             f.write_str("]")?;
         }
         // For n-dimensional arrays, we proceed recursively
@@ -170,7 +340,6 @@ where
                 format_array_inner(
                     view.index_axis_dyn(Index0D::new(index)),
                     f,
-                    format.clone(),
                     fmt_opt,
                     depth + 1,
                     full_ndim,
@@ -182,6 +351,43 @@ where
     Ok(())
 }
 
+fn format_matrix<A, S, D>(
+    matrix: &Matrix<S, D>,
+    f: &mut fmt::Formatter<'_>,
+    options: &PrintOptions,
+    debug: bool,
+) -> fmt::Result
+where
+    A: Num,
+    D: DimTrait,
+    S: Memory<Item = A> + ToViewMemory,
+{
+    if options.header {
+        write_shape_stride_dtype(matrix, f)?;
+        f.write_str("\n")?;
+    }
+    let fmt_opt = FormatOptions::new(matrix.shape().num_elm(), f, options, debug);
+    format_array(matrix, f, &fmt_opt)
+}
+
+fn write_shape_stride_dtype<A, S, D>(
+    matrix: &Matrix<S, D>,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result
+where
+    A: Num,
+    D: DimTrait,
+    S: Memory<Item = A> + ToViewMemory,
+{
+    write!(
+        f,
+        "shape={:?}, stride={:?}, dtype={}",
+        matrix.shape().slice(),
+        matrix.stride().slice(),
+        std::any::type_name::<A>()
+    )
+}
+
 // NOTE: We can impl other fmt traits here
 /// Format the array using `Display` and apply the formatting parameters used
 /// to each element.
@@ -193,42 +399,84 @@ where
     S: Memory<Item = A> + ToViewMemory,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let fmt_opt = FormatOptions::default_for_array(self.shape().num_elm(), f.alternate());
-        format_array(self, f, <_>::fmt, &fmt_opt)
+        format_matrix(self, f, &print_options(), false)
     }
 }
 
 /// Format the array using `Debug` and apply the formatting parameters used
 /// to each element.
 ///
-/// The array is shown in multiline style.
+/// The array is shown in multiline style, followed by `, shape=.., strides=..` unless
+/// the header is enabled.
 impl<A: fmt::Debug, S, D: DimTrait> fmt::Debug for Matrix<S, D>
 where
     A: Num,
     S: Memory<Item = A> + ToViewMemory,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let fmt_opt = FormatOptions::default_for_array(self.shape().num_elm(), f.alternate());
-        format_array(self, f, <_>::fmt, &fmt_opt)?;
+        let options = print_options();
+        format_matrix(self, f, &options, true)?;
 
         // Add extra information for Debug
-        write!(f, ", shape={:?}, strides={:?}", self.shape(), self.stride(),)?;
-        // match self.shape().len() {
-        //     Some(ndim) => write!(f, ", const ndim={}", ndim)?,
-        //     None => write!(f, ", dynamic ndim={}", self.shape().len())?,
-        // }
+        // 既存の出力を読み取る側のために、ヘッダーとは別に従来の形式のまま書く
+        if !options.header {
+            write!(f, ", shape={:?}, strides={:?}", self.shape(), self.stride())?;
+        }
         Ok(())
     }
 }
 
+/// `Display` adapter returned by [`Matrix::display_with`].
+pub struct MatrixDisplay<'a, S, D> {
+    matrix: &'a Matrix<S, D>,
+    options: PrintOptions,
+}
+
+impl<'a, A, S, D> fmt::Display for MatrixDisplay<'a, S, D>
+where
+    A: Num,
+    D: DimTrait,
+    S: Memory<Item = A> + ToViewMemory,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_matrix(self.matrix, f, &self.options, false)
+    }
+}
+
+impl<A, S, D> Matrix<S, D>
+where
+    A: Num,
+    D: DimTrait,
+    S: Memory<Item = A> + ToViewMemory,
+{
+    /// Displays the matrix with `options` instead of the global print options.
+    pub fn display_with(&self, options: PrintOptions) -> MatrixDisplay<'_, S, D> {
+        MatrixDisplay {
+            matrix: self,
+            options,
+        }
+    }
+}
+
 #[cfg(test)]
 mod matrix_format {
+    use std::sync::{Mutex, MutexGuard};
+
     use crate::{
         constructor::ones::Ones,
         matrix::OwnedMatrix,
-        matrix_impl::{OwnedMatrix1D, OwnedMatrix2D, OwnedMatrix3D, OwnedMatrix4D},
+        matrix_impl::{OwnedMatrix1D, OwnedMatrix2D, OwnedMatrix3D, OwnedMatrix4D, OwnedMatrixDyn},
+        operation::transpose::TransposeInplace,
     };
 
+    use super::{print_options, reset_print_options, set_print_options, PrintOptions};
+
+    /// The global print options are shared by every test in this module.
+    fn lock_print_options() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     fn assert_str_eq(expected: &str, actual: &str) {
         // use assert to avoid printing the strings twice on failure
         assert!(
@@ -241,18 +489,21 @@ mod matrix_format {
 
     #[test]
     fn small_array_1d() {
+        let _lock = lock_print_options();
         let a = OwnedMatrix1D::from_vec(vec![1., 2., 3., 4., 5.], [5]);
         assert_eq!(format!("{}", a), "[1, 2, 3, 4, 5]");
     }
 
     #[test]
     fn mid_array_1d() {
+        let _lock = lock_print_options();
         let a = OwnedMatrix1D::from_vec(vec![1., 2., 3., 4., 5., 6., 7., 8., 9., 10.], [10]);
         assert_eq!(format!("{}", a), "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]");
     }
 
     #[test]
     fn large_array_1d() {
+        let _lock = lock_print_options();
         let mut v = Vec::new();
         for i in 1..=1000 {
             v.push(i as f32);
@@ -266,6 +517,7 @@ mod matrix_format {
 
     #[test]
     fn dim_2_last_axis_overflow() {
+        let _lock = lock_print_options();
         let a = OwnedMatrix2D::<f32>::ones([22, 24]);
         let actual = format!("{}", a);
         let expected = "\
@@ -284,6 +536,7 @@ mod matrix_format {
     }
     #[test]
     fn dim_3_overflow_most() {
+        let _lock = lock_print_options();
         let mut v = Vec::new();
         for i in 0..7 {
             for j in 0..11 {
@@ -383,6 +636,7 @@ mod matrix_format {
 
     #[test]
     fn dim_4_overflow_outer() {
+        let _lock = lock_print_options();
         // let a = Array4::from_shape_fn((10, 10, 3, 3), |(i, j, k, l)| i + j + k + l);
         let mut v = Vec::new();
         for i in 0..10 {
@@ -566,4 +820,112 @@ mod matrix_format {
    [20, 21, 22]]]]";
         assert_str_eq(expected, &actual);
     }
+
+    #[test]
+    fn precision_and_scientific() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2.5, 1234.5], [3]);
+        let options = PrintOptions {
+            precision: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(a.display_with(options).to_string(), "[1.00, 2.50, 1234.50]");
+        let options = PrintOptions {
+            precision: Some(1),
+            scientific: true,
+            ..Default::default()
+        };
+        assert_eq!(a.display_with(options).to_string(), "[1.0e0, 2.5e0, 1.2e3]");
+        // the precision in the format string wins
+        assert_eq!(
+            format!("{:.1}", a.display_with(options)),
+            "[1.0e0, 2.5e0, 1.2e3]"
+        );
+        // f32はf64に広げずに書くので、余計な桁が出ない
+        let b = OwnedMatrixDyn::from_vec(vec![0.1f32, 2.5], [2]);
+        let options = PrintOptions {
+            scientific: true,
+            ..Default::default()
+        };
+        assert_eq!(b.display_with(options).to_string(), "[1e-1, 2.5e0]");
+        let options = PrintOptions {
+            precision: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(format!("{:.0}", a.display_with(options)), "[1, 2, 1234]");
+    }
+
+    #[test]
+    fn threshold_and_edgeitems() {
+        let a = OwnedMatrixDyn::from_vec((1..=10).map(|x| x as f32).collect(), [10]);
+        let options = PrintOptions {
+            threshold: 5,
+            edgeitems: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(a.display_with(options).to_string(), "[1, 2, ..., 9, 10]");
+        // alternate flag never summarizes
+        assert_eq!(
+            format!("{:#}", a.display_with(options)),
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]"
+        );
+    }
+
+    #[test]
+    fn linewidth_wraps_rows() {
+        let a = OwnedMatrixDyn::from_vec((1..=10).map(|x| x as f32).collect(), [10]);
+        let options = PrintOptions {
+            linewidth: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            a.display_with(options).to_string(),
+            "[1, 2, 3, 4, 5, 6,\n 7, 8, 9, 10]"
+        );
+
+        let a = OwnedMatrixDyn::from_vec((1..=8).map(|x| x as f32).collect(), [2, 4]);
+        let options = PrintOptions {
+            linewidth: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            a.display_with(options).to_string(),
+            "[[1, 2, 3,\n  4],\n [5, 6, 7,\n  8]]"
+        );
+    }
+
+    #[test]
+    fn header_and_debug() {
+        let _lock = lock_print_options();
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let t = a.transepose_by_index(&[1, 0]);
+        let options = PrintOptions {
+            header: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            t.display_with(options).to_string(),
+            "shape=[3, 2], stride=[1, 3], dtype=f64\n[[1, 4],\n [2, 5],\n [3, 6]]"
+        );
+        assert_eq!(
+            format!("{:?}", t),
+            "[[1.0, 4.0],\n [2.0, 5.0],\n [3.0, 6.0]], \
+             shape=DimDyn { dim: [3, 2, 0, 0, 0, 0], len: 2 }, \
+             strides=DimDyn { dim: [1, 3, 0, 0, 0, 0], len: 2 }"
+        );
+    }
+
+    #[test]
+    fn global_print_options() {
+        let _lock = lock_print_options();
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        set_print_options(PrintOptions {
+            precision: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(print_options().precision, Some(3));
+        assert_eq!(format!("{}", a), "[1.000, 2.000]");
+        reset_print_options();
+        assert_eq!(print_options(), PrintOptions::default());
+        assert_eq!(format!("{}", a), "[1, 2]");
+    }
 }
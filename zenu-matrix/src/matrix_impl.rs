@@ -216,6 +216,17 @@ impl<M: Owned, S: DimTrait> OwnedMatrix for Matrix<M, S> {
             stride,
         }
     }
+
+    fn from_fn<I: Into<Self::Dim>, F: FnMut(usize) -> Self::Item>(dim: I, f: F) -> Self {
+        let dim = dim.into();
+        let stride = default_stride(dim);
+        let memory = M::from_fn(dim.num_elm(), f);
+        Matrix {
+            memory,
+            shape: dim,
+            stride,
+        }
+    }
 }
 
 impl<M: ToViewMemory, D: DimTrait, S: SliceTrait<Dim = D>> MatrixSlice<S> for Matrix<M, D> {
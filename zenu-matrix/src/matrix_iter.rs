@@ -1,12 +1,19 @@
+use std::marker::PhantomData;
+
 use crate::{
     constructor::zeros::Zeros,
     dim::{cal_offset, DimDyn, DimTrait},
-    matrix::{MatrixBase, ToViewMatrix, ToViewMutMatrix},
+    index::index_dyn_impl::Index,
+    matrix::{
+        AsMutPtr, AsPtr, IndexAxisDyn, MatrixBase, OwnedMatrix, ToViewMatrix, ToViewMutMatrix,
+    },
     matrix_impl::{Matrix, OwnedMatrixDyn},
     memory::{ToViewMemory, ToViewMutMemory},
-    memory_impl::{OwnedMem, ViewMutMem},
+    memory_impl::{OwnedMem, ViewMem, ViewMutMem},
     num::Num,
-    operation::copy_from::CopyFrom,
+    operation::{
+        copy_from::CopyFrom, to_default_stride::ToDefaultStride, transpose::TransposeInplace,
+    },
     shape_stride::ShapeStride,
 };
 
@@ -115,6 +122,8 @@ pub trait MatrixIter<T: Num> {
     fn map_axis_mut<F>(&mut self, axis: usize, fn_map: F)
     where
         F: FnMut(Matrix<ViewMutMem<T>, DimDyn>);
+    /// 全要素への可変参照をrow-major順に返す
+    fn iter_mut(&mut self) -> IterMut<'_, T>;
 }
 
 impl<T: Num, M: ToViewMutMemory<Item = T> + ToViewMemory> MatrixIter<T> for Matrix<M, DimDyn> {
@@ -139,6 +148,403 @@ impl<T: Num, M: ToViewMutMemory<Item = T> + ToViewMemory> MatrixIter<T> for Matr
         let mut map_axis = MapAxis::new(mut_matrix, axis, fn_map);
         map_axis.apply();
     }
+
+    fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let shape = self.shape();
+        let stride = self.stride();
+        let ptr = self.to_view_mut().as_mut_ptr();
+        IterMut {
+            ptr,
+            shape,
+            stride,
+            index: DimDyn::from(vec![0; shape.len()].as_slice()),
+            remaining: shape.num_elm(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// 読み取り専用の要素単位のIterator
+pub trait MatrixElementIter<T: Num> {
+    /// 全要素をrow-major順に返す
+    fn iter(&self) -> Iter<'_, T>;
+    /// 各要素に`f`を適用した新しいMatrixを返す
+    fn map<F>(&self, f: F) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        F: FnMut(T) -> T;
+    /// 2つのMatrixをbroadcastしながら要素ごとに`f`を適用する
+    fn zip_with<M, F>(&self, other: &Matrix<M, DimDyn>, f: F) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        M: ToViewMemory<Item = T>,
+        F: FnMut(T, T) -> T;
+    fn fold<B, F>(&self, init: B, f: F) -> B
+    where
+        F: FnMut(B, T) -> B;
+    /// `axis`に沿ってindexしたsub viewを順に返す
+    fn axis_iter(&self, axis: usize) -> AxisIter<'_, Self>
+    where
+        Self: Sized;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> MatrixElementIter<T> for Matrix<M, DimDyn> {
+    fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self.to_view())
+    }
+
+    fn map<F>(&self, f: F) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        F: FnMut(T) -> T,
+    {
+        let vec = self.iter().map(f).collect();
+        Matrix::from_vec(vec, self.shape())
+    }
+
+    fn zip_with<V, F>(&self, other: &Matrix<V, DimDyn>, mut f: F) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        V: ToViewMemory<Item = T>,
+        F: FnMut(T, T) -> T,
+    {
+        let shape = broadcast_shape(self.shape(), other.shape());
+        let lhs = Iter::new(broadcast_to(self.to_view(), shape));
+        let rhs = Iter::new(broadcast_to(other.to_view(), shape));
+        let vec = lhs.zip(rhs).map(|(a, b)| f(a, b)).collect();
+        Matrix::from_vec(vec, shape)
+    }
+
+    fn fold<B, F>(&self, init: B, f: F) -> B
+    where
+        F: FnMut(B, T) -> B,
+    {
+        self.iter().fold(init, f)
+    }
+
+    fn axis_iter(&self, axis: usize) -> AxisIter<'_, Self> {
+        let shape = self.shape();
+        assert!(
+            axis < shape.len(),
+            "axis {} is out of range for a {}-D matrix",
+            axis,
+            shape.len()
+        );
+        AxisIter {
+            matrix: self,
+            axis,
+            index: 0,
+            len: shape[axis],
+        }
+    }
+}
+
+fn next_index(index: &mut DimDyn, shape: DimDyn) {
+    for i in (0..shape.len()).rev() {
+        index[i] += 1;
+        if index[i] < shape[i] {
+            return;
+        }
+        index[i] = 0;
+    }
+}
+
+pub struct Iter<'a, T: Num> {
+    matrix: Matrix<ViewMem<'a, T>, DimDyn>,
+    index: DimDyn,
+    remaining: usize,
+}
+
+impl<'a, T: Num> Iter<'a, T> {
+    fn new(matrix: Matrix<ViewMem<'a, T>, DimDyn>) -> Self {
+        let shape = matrix.shape();
+        Self {
+            matrix,
+            index: DimDyn::from(vec![0; shape.len()].as_slice()),
+            remaining: shape.num_elm(),
+        }
+    }
+}
+
+impl<'a, T: Num> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let offset = cal_offset(self.index, self.matrix.stride());
+        let value = unsafe { *self.matrix.as_ptr().add(offset) };
+        next_index(&mut self.index, self.matrix.shape());
+        self.remaining -= 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Num> ExactSizeIterator for Iter<'a, T> {}
+
+pub struct IterMut<'a, T: Num> {
+    ptr: *mut T,
+    shape: DimDyn,
+    stride: DimDyn,
+    index: DimDyn,
+    remaining: usize,
+    _phantom: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: Num> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let offset = cal_offset(self.index, self.stride);
+        // 可変なMatrixのstrideは要素が重ならないので、同じ要素への参照は一度しか返さない
+        let value = unsafe { &mut *self.ptr.add(offset) };
+        next_index(&mut self.index, self.shape);
+        self.remaining -= 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Num> ExactSizeIterator for IterMut<'a, T> {}
+
+pub struct AxisIter<'a, M> {
+    matrix: &'a M,
+    axis: usize,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, M: IndexAxisDyn<Index>> Iterator for AxisIter<'a, M> {
+    type Item = M::Output<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            return None;
+        }
+        let sub = self
+            .matrix
+            .index_axis_dyn(Index::new(self.axis, self.index));
+        self.index += 1;
+        Some(sub)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, M: IndexAxisDyn<Index>> ExactSizeIterator for AxisIter<'a, M> {}
+
+/// numpyと同じ規則でbroadcast後のshapeを求める
+fn broadcast_shape(a: DimDyn, b: DimDyn) -> DimDyn {
+    let len = a.len().max(b.len());
+    let mut shape = DimDyn::default();
+    for i in 0..len {
+        let a_dim = if i + a.len() >= len {
+            a[i + a.len() - len]
+        } else {
+            1
+        };
+        let b_dim = if i + b.len() >= len {
+            b[i + b.len() - len]
+        } else {
+            1
+        };
+        let dim = if a_dim == b_dim || b_dim == 1 {
+            a_dim
+        } else if a_dim == 1 {
+            b_dim
+        } else {
+            panic!("shapes {:?} and {:?} cannot be broadcast together", a, b);
+        };
+        shape.push_dim(dim);
+    }
+    shape
+}
+
+/// broadcastされる次元のstrideを0にしたviewを返す
+fn broadcast_to<T: Num>(
+    matrix: Matrix<ViewMem<T>, DimDyn>,
+    shape: DimDyn,
+) -> Matrix<ViewMem<T>, DimDyn> {
+    let diff = shape.len() - matrix.shape().len();
+    let mut stride = DimDyn::default();
+    for i in 0..shape.len() {
+        if i < diff || matrix.shape()[i - diff] == 1 {
+            stride.push_dim(0);
+        } else {
+            stride.push_dim(matrix.stride()[i - diff]);
+        }
+    }
+    let mut matrix = matrix;
+    matrix.update_shape_stride(ShapeStride::new(shape, stride));
+    matrix
+}
+
+/// `axis`を最後に移動させるpermutation
+pub(crate) fn axis_to_last_perm(shape: DimDyn, axis: usize) -> Vec<usize> {
+    assert!(
+        axis < shape.len(),
+        "axis {} is out of range for a {}-D matrix",
+        axis,
+        shape.len()
+    );
+    (0..shape.len())
+        .filter(|&i| i != axis)
+        .chain(std::iter::once(axis))
+        .collect()
+}
+
+/// `perm`で並べ替えた`x`の要素をdefault strideの順番で集める。
+/// `perm`が`axis_to_last_perm`なら各laneが連続に並ぶ
+pub(crate) fn gather_lanes<T: Num, M: ToViewMemory<Item = T>>(
+    x: &Matrix<M, DimDyn>,
+    perm: &[usize],
+) -> Vec<T> {
+    x.transepose_by_index(perm).iter().collect()
+}
+
+/// `perm`で並べ替えた形で計算した`output`を元の軸の順番のMatrixに戻す
+pub(crate) fn restore_lanes<T: Num>(
+    output: Vec<T>,
+    shape: DimDyn,
+    perm: &[usize],
+    out_len: usize,
+) -> Matrix<OwnedMem<T>, DimDyn> {
+    let mut permuted_shape = DimDyn::default();
+    for &p in &perm[..perm.len() - 1] {
+        permuted_shape.push_dim(shape[p]);
+    }
+    permuted_shape.push_dim(out_len);
+    let mut inverse_perm = vec![0; perm.len()];
+    for (i, &p) in perm.iter().enumerate() {
+        inverse_perm[p] = i;
+    }
+    Matrix::<OwnedMem<T>, DimDyn>::from_vec(output, permuted_shape)
+        .transepose_by_index(&inverse_perm)
+        .to_default_stride()
+}
+
+/// `axis`に沿った1次元のlaneそれぞれに`f`を適用する
+///
+/// `f`は入力laneと長さ`out_len`の出力laneを受け取る。
+/// 戻り値は`axis`の長さが`out_len`になったdefault strideのMatrix
+pub(crate) fn map_lanes<T, M, F>(
+    x: &Matrix<M, DimDyn>,
+    axis: usize,
+    out_len: usize,
+    mut f: F,
+) -> Matrix<OwnedMem<T>, DimDyn>
+where
+    T: Num,
+    M: ToViewMemory<Item = T>,
+    F: FnMut(&[T], &mut [T]),
+{
+    let shape = x.shape();
+    // axisを最後に移動してlaneを連続にする
+    let perm = axis_to_last_perm(shape, axis);
+    let input = gather_lanes(x, &perm);
+
+    let len = shape[axis];
+    let num_lanes = perm[..perm.len() - 1]
+        .iter()
+        .map(|&p| shape[p])
+        .product::<usize>();
+    let mut output = vec![T::zero(); num_lanes * out_len];
+    for lane in 0..num_lanes {
+        f(
+            &input[lane * len..(lane + 1) * len],
+            &mut output[lane * out_len..(lane + 1) * out_len],
+        );
+    }
+    restore_lanes(output, shape, &perm, out_len)
+}
+
+/// 同じshapeの2つのMatrixの対応するlaneに`f`を適用する。それ以外は`map_lanes`と同じ
+pub(crate) fn zip_lanes<T, M, N, F>(
+    a: &Matrix<M, DimDyn>,
+    b: &Matrix<N, DimDyn>,
+    axis: usize,
+    out_len: usize,
+    mut f: F,
+) -> Matrix<OwnedMem<T>, DimDyn>
+where
+    T: Num,
+    M: ToViewMemory<Item = T>,
+    N: ToViewMemory<Item = T>,
+    F: FnMut(&[T], &[T], &mut [T]),
+{
+    let shape = a.shape();
+    assert_eq!(
+        shape.slice(),
+        b.shape().slice(),
+        "both matrices must have the same shape"
+    );
+    let perm = axis_to_last_perm(shape, axis);
+    let lhs = gather_lanes(a, &perm);
+    let rhs = gather_lanes(b, &perm);
+
+    let len = shape[axis];
+    let num_lanes = perm[..perm.len() - 1]
+        .iter()
+        .map(|&p| shape[p])
+        .product::<usize>();
+    let mut output = vec![T::zero(); num_lanes * out_len];
+    for lane in 0..num_lanes {
+        f(
+            &lhs[lane * len..(lane + 1) * len],
+            &rhs[lane * len..(lane + 1) * len],
+            &mut output[lane * out_len..(lane + 1) * out_len],
+        );
+    }
+    restore_lanes(output, shape, &perm, out_len)
+}
+
+/// 各laneを1つの値に縮約する。`axis`が`None`なら全要素を1つのlaneとして扱う
+pub(crate) fn reduce_lanes<T, M, F>(
+    x: &Matrix<M, DimDyn>,
+    axis: Option<usize>,
+    keep_dim: bool,
+    mut f: F,
+) -> Matrix<OwnedMem<T>, DimDyn>
+where
+    T: Num,
+    M: ToViewMemory<Item = T>,
+    F: FnMut(&[T]) -> T,
+{
+    let shape = x.shape();
+    let mut reduced_shape = DimDyn::default();
+    match axis {
+        Some(axis) => {
+            let reduced = map_lanes(x, axis, 1, |lane, out| out[0] = f(lane));
+            for (i, &len) in shape.slice().iter().enumerate() {
+                if i != axis {
+                    reduced_shape.push_dim(len);
+                } else if keep_dim {
+                    reduced_shape.push_dim(1);
+                }
+            }
+            let vec = reduced.iter().collect();
+            Matrix::from_vec(vec, reduced_shape)
+        }
+        None => {
+            let all = x.iter().collect::<Vec<_>>();
+            if keep_dim {
+                for _ in 0..shape.len() {
+                    reduced_shape.push_dim(1);
+                }
+            }
+            Matrix::from_vec(vec![f(&all)], reduced_shape)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -250,3 +656,83 @@ mod map_axis {
         assert_eq!(diff, 0.);
     }
 }
+
+#[cfg(test)]
+mod element_iter {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::{MatrixElementIter, MatrixIter};
+
+    #[test]
+    fn iter_transposed() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let t = a.transepose_by_index(&[1, 0]);
+        let v = t.iter().collect::<Vec<_>>();
+        assert_eq!(v, vec![1., 4., 2., 5., 3., 6.]);
+        assert_eq!(t.iter().len(), 6);
+    }
+
+    #[test]
+    fn iter_0d() {
+        let a = OwnedMatrixDyn::from_vec(vec![3.], []);
+        assert_eq!(a.iter().collect::<Vec<_>>(), vec![3.]);
+    }
+
+    #[test]
+    fn iter_mut_double() {
+        let mut a = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        for x in a.iter_mut() {
+            *x *= 2.;
+        }
+        let ans = OwnedMatrixDyn::from_vec(vec![2., 4., 6., 8.], [2, 2]);
+        assert_eq!((a.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn map_square() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        let b = a.map(|x| x * x);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 4., 9., 16.], [2, 2]);
+        assert_eq!((b.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn zip_with_broadcast() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3.], [3, 1]);
+        let b = OwnedMatrixDyn::from_vec(vec![10., 20.], [2]);
+        let c = a.zip_with(&b, |x, y| x + y);
+        assert_eq!(c.shape().slice(), [3, 2]);
+        let ans = OwnedMatrixDyn::from_vec(vec![11., 21., 12., 22., 13., 23.], [3, 2]);
+        assert_eq!((c.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zip_with_shape_mismatch() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3.], [3]);
+        let b = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        a.zip_with(&b, |x, y| x + y);
+    }
+
+    #[test]
+    fn fold_max() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 5., 3., 4.], [2, 2]);
+        let max = a.fold(f64::MIN, |acc, x| acc.max(x));
+        assert_eq!(max, 5.);
+    }
+
+    #[test]
+    fn axis_iter_3d() {
+        let a = OwnedMatrixDyn::from_vec((0..12).map(|x| x as f64).collect(), [2, 3, 2]);
+        let subs = a.axis_iter(1).collect::<Vec<_>>();
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[1].shape().slice(), [2, 2]);
+        assert_eq!(subs[1].index_item([0, 0]), 2.);
+        assert_eq!(subs[1].index_item([1, 1]), 9.);
+    }
+}
@@ -51,6 +51,8 @@ pub trait ToOwnedMemory: Memory {
 /// Memoryの中でも値を保持するメモリを表すトレイト
 pub trait Owned: Memory + ToViewMemory + ToViewMutMemory + Clone + ToOwnedMemory + 'static {
     fn from_vec(vec: Vec<Self::Item>) -> Self;
+    /// `len`要素のメモリを確保し、`i`番目の要素を`f(i)`で初期化する
+    fn from_fn<F: FnMut(usize) -> Self::Item>(len: usize, f: F) -> Self;
 }
 
 /// Memoryの中でも値を保持するメモリを表すトレイト
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    cpu_allocator,
     cpu_blas::CpuBlas,
     cpu_element_wise::CpuElementWise,
     memory::{
@@ -54,16 +55,13 @@ impl<T: Num> MemoryAccessor for Cpu<T> {
     }
 
     fn clone_ptr(&self, ptr: NonNull<Self::Item>, len: usize) -> NonNull<Self::Item> {
-        let vec = unsafe { Vec::from_raw_parts(ptr.as_ptr(), len, len) };
-        let mut vec_c = vec.clone();
-        std::mem::forget(vec);
-        let ptr = NonNull::new(vec_c.as_mut_ptr()).unwrap();
-        std::mem::forget(vec_c);
-        ptr
+        let cloned = cpu_allocator::allocate::<T>(len);
+        unsafe { std::ptr::copy_nonoverlapping(ptr.as_ptr(), cloned.as_ptr(), len) };
+        cloned
     }
 
     fn drop(&self, ptr: *const Self::Item, len: usize) {
-        let _ = unsafe { Vec::from_raw_parts(ptr as *mut T, len, len) };
+        cpu_allocator::deallocate(ptr, len);
     }
 
     fn offset_ptr(&self, ptr: NonNull<Self::Item>, offset: usize) -> NonNull<Self::Item> {
@@ -213,9 +211,9 @@ impl<T: Num, A: MemoryAccessor<Item = T>> ToOwnedMemory for OwnedMem<T, A> {
 
 impl<T: Num, A: MemoryAccessor<Item = T>> Owned for OwnedMem<T, A> {
     fn from_vec(vec: Vec<Self::Item>) -> Self {
-        let ptr = unsafe { NonNull::new_unchecked(vec.as_ptr() as *mut T) };
+        // Vecのbufferをコピーせずに所有する。cpu_allocator::deallocateがVecとして解放する
         let length = vec.len();
-        std::mem::forget(vec);
+        let ptr = into_raw_buffer(vec);
         Self {
             ptr,
             offset: 0,
@@ -223,6 +221,45 @@ impl<T: Num, A: MemoryAccessor<Item = T>> Owned for OwnedMem<T, A> {
             accessor: Cpu::new(),
         }
     }
+
+    fn from_fn<F: FnMut(usize) -> Self::Item>(len: usize, mut f: F) -> Self {
+        let ptr = cpu_allocator::allocate::<T>(len);
+        for i in 0..len {
+            unsafe { ptr.as_ptr().add(i).write(f(i)) };
+        }
+        Self {
+            ptr,
+            offset: 0,
+            length: len,
+            accessor: Cpu::new(),
+        }
+    }
+}
+
+/// capacityを長さに揃えてからVecのbufferを取り出す
+fn into_raw_buffer<T>(vec: Vec<T>) -> NonNull<T> {
+    let buffer = Box::into_raw(vec.into_boxed_slice());
+    NonNull::new(buffer.cast::<T>()).unwrap()
+}
+
+#[cfg(any(feature = "ndarray", feature = "mmap"))]
+impl<T: Num> OwnedMem<T, Cpu<T>> {
+    /// 他のライブラリが確保したメモリを指すOwnedMemを作る
+    /// メモリは所有していないので、`ManuallyDrop`で包んでdropされないようにする
+    ///
+    /// # Safety
+    /// `ptr`から`length`要素が、返り値を使っている間有効である必要がある
+    pub(crate) unsafe fn from_raw_parts_borrowed(
+        ptr: NonNull<T>,
+        length: usize,
+    ) -> std::mem::ManuallyDrop<Self> {
+        std::mem::ManuallyDrop::new(Self {
+            ptr,
+            offset: 0,
+            length,
+            accessor: Cpu::new(),
+        })
+    }
 }
 
 impl<T: Num, A: MemoryAccessor<Item = T>> Drop for OwnedMem<T, A> {
@@ -273,17 +310,13 @@ where
         D: serde::Deserializer<'de>,
     {
         let ser = OwnedMemSer::<T>::deserialize(deserializer)?;
-        let mut data = ser.data;
-        let ptr = NonNull::new(data.as_mut_ptr()).ok_or_else(|| {
-            serde::de::Error::custom("Failed to create NonNull pointer from deserialized data")
-        })?;
+        let ptr = into_raw_buffer(ser.data);
         let owned_mem = OwnedMem {
             ptr,
             offset: ser.offset,
             length: ser.length,
             accessor: Cpu::new(),
         };
-        std::mem::forget(data);
         Ok(owned_mem)
     }
 }
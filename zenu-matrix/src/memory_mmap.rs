@@ -0,0 +1,233 @@
+//! Read-only memory backed by a memory mapped file.
+//!
+//! Enabled with the `mmap` feature.
+//!
+//! [`MmapMem`] maps a file of raw, native endian values (e.g. written by numpy's
+//! `ndarray.tofile`) and exposes it through [`ToViewMemory`], so a file backed matrix can
+//! be sliced, indexed, iterated over in batches and copied from like any other matrix
+//! without reading the whole file up front. It is read-only: there is no
+//! `ToViewMutMemory` implementation, and [`ToOwnedMemory`] copies into an [`OwnedMem`].
+//!
+//! ```no_run
+//! use zenu_matrix::{
+//!     index::Index0D,
+//!     matrix::{IndexAxisDyn, ToViewMatrix},
+//!     matrix_impl::Matrix,
+//!     memory_mmap::MmapMem,
+//!     operation::to_default_stride::ToDefaultStride,
+//! };
+//!
+//! let images = Matrix::<MmapMem<f32>, _>::from_mmap_file("train.bin", 0, [60000, 784]).unwrap();
+//! let first = images.index_axis_dyn(Index0D::new(0)).to_default_stride();
+//! ```
+use std::{fs::File, io, mem::ManuallyDrop, path::Path, ptr::NonNull};
+
+use memmap2::Mmap;
+
+use crate::{
+    cpu_blas::CpuBlas,
+    cpu_element_wise::CpuElementWise,
+    dim::{default_stride, DimDyn, DimTrait},
+    matrix_impl::Matrix,
+    memory::{Memory, ToOwnedMemory, ToViewMemory},
+    memory_impl::{OwnedMem, ViewMem},
+    num::Num,
+};
+
+pub struct MmapMem<T: Num> {
+    // mmapの領域を指しているだけなので、dropしてはいけない
+    mem: ManuallyDrop<OwnedMem<T>>,
+    // memより後に宣言してmemが使われなくなってからunmapする
+    _mmap: Mmap,
+}
+
+impl<T: Num> MmapMem<T> {
+    /// Maps `len` values of type `T` starting `offset_bytes` into the file at `path`.
+    ///
+    /// Fails if the file is too short or if `offset_bytes` is not aligned for `T`.
+    pub fn open<P: AsRef<Path>>(path: P, offset_bytes: usize, len: usize) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the mapping is read-only. Other processes truncating or writing to the
+        // file while it is mapped is undefined behavior, as with any mmap.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let needed = len
+            .checked_mul(std::mem::size_of::<T>())
+            .and_then(|bytes| bytes.checked_add(offset_bytes))
+            .ok_or_else(|| invalid_input("mapped region size overflows usize"))?;
+        if mmap.len() < needed {
+            return Err(invalid_input(&format!(
+                "file has {} bytes, but {} bytes are needed",
+                mmap.len(),
+                needed
+            )));
+        }
+        if !offset_bytes.is_multiple_of(std::mem::align_of::<T>()) {
+            return Err(invalid_input(&format!(
+                "offset {} is not aligned to {} bytes",
+                offset_bytes,
+                std::mem::align_of::<T>()
+            )));
+        }
+
+        let ptr = if len == 0 {
+            NonNull::dangling()
+        } else {
+            NonNull::new(unsafe { mmap.as_ptr().add(offset_bytes) } as *mut T).unwrap()
+        };
+        let mem = unsafe { OwnedMem::from_raw_parts_borrowed(ptr, len) };
+        Ok(Self { mem, _mmap: mmap })
+    }
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+impl<T: Num> Memory for MmapMem<T> {
+    type Item = T;
+    type Blas = CpuBlas<T>;
+    type ElmentWise = CpuElementWise<T>;
+
+    fn len(&self) -> usize {
+        self.mem.len()
+    }
+
+    fn as_ptr(&self) -> *const Self::Item {
+        self.mem.as_ptr()
+    }
+
+    fn as_ptr_offset(&self, offset: usize) -> *const Self::Item {
+        self.mem.as_ptr_offset(offset)
+    }
+
+    fn value_offset(&self, offset: usize) -> Self::Item {
+        self.mem.value_offset(offset)
+    }
+
+    fn get_offset(&self) -> usize {
+        self.mem.get_offset()
+    }
+
+    fn set_offset(&mut self, offset: usize) {
+        self.mem.set_offset(offset);
+    }
+}
+
+impl<T: Num> ToViewMemory for MmapMem<T> {
+    fn to_view(&self, offset: usize) -> ViewMem<'_, T> {
+        self.mem.to_view(offset)
+    }
+}
+
+impl<T: Num> ToOwnedMemory for MmapMem<T> {
+    type Owned = OwnedMem<T>;
+
+    fn to_owned_memory(&self) -> Self::Owned {
+        // OwnedMemのcloneは新しく確保した領域にコピーする
+        (*self.mem).clone()
+    }
+}
+
+impl<T: Num> Matrix<MmapMem<T>, DimDyn> {
+    /// Maps a file of raw values as a default stride matrix of shape `shape`.
+    pub fn from_mmap_file<P: AsRef<Path>, I: Into<DimDyn>>(
+        path: P,
+        offset_bytes: usize,
+        shape: I,
+    ) -> io::Result<Self> {
+        let shape = shape.into();
+        let memory = MmapMem::open(path, offset_bytes, shape.num_elm())?;
+        Ok(Matrix::new(memory, shape, default_stride(shape)))
+    }
+}
+
+#[cfg(test)]
+mod memory_mmap {
+    use std::{io::Write, path::PathBuf};
+
+    use crate::{
+        constructor::zeros::Zeros,
+        dim::DimTrait,
+        index::Index0D,
+        matrix::{
+            IndexAxisDyn, IndexItem, MatrixBase, MatrixSliceDyn, OwnedMatrix, ToOwnedMatrix,
+            ToViewMatrix, ToViewMutMatrix,
+        },
+        matrix_impl::{Matrix, OwnedMatrixDyn},
+        operation::{asum::Asum, copy_from::CopyFrom},
+        slice_dynamic,
+    };
+
+    use super::MmapMem;
+
+    fn write_tmp(name: &str, header: &[u8], values: &[f32]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("zenu_mmap_{}_{}", std::process::id(), name));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(header).unwrap();
+        for v in values {
+            file.write_all(&v.to_ne_bytes()).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn read_and_slice() {
+        let values = (0..12).map(|x| x as f32).collect::<Vec<_>>();
+        let path = write_tmp("slice", &[], &values);
+        let m = Matrix::<MmapMem<f32>, _>::from_mmap_file(&path, 0, [3, 4]).unwrap();
+        assert_eq!(m.shape().slice(), [3, 4]);
+        assert_eq!(m.index_item([2, 1]), 9.);
+
+        let s = m.slice_dyn(slice_dynamic!(1.., ..;2));
+        assert_eq!(s.shape().slice(), [2, 2]);
+        assert_eq!(s.index_item([1, 1]), 10.);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn header_offset_and_batches() {
+        let values = (0..8).map(|x| x as f32).collect::<Vec<_>>();
+        let path = write_tmp("batch", &[0; 8], &values);
+        let m = Matrix::<MmapMem<f32>, _>::from_mmap_file(&path, 8, [4, 2]).unwrap();
+
+        let mut batch = OwnedMatrixDyn::<f32>::zeros([2, 2]);
+        batch
+            .to_view_mut()
+            .copy_from(&m.slice_dyn(slice_dynamic!(2..4, ..)));
+        let ans = OwnedMatrixDyn::from_vec(vec![4., 5., 6., 7.], [2, 2]);
+        assert_eq!((batch.to_view() - ans.to_view()).asum(), 0.);
+
+        let row = m.index_axis_dyn(Index0D::new(1));
+        assert_eq!(row.index_item([1]), 3.);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn to_owned_copies() {
+        let values = vec![1., 2., 3.];
+        let path = write_tmp("owned", &[], &values);
+        let m = Matrix::<MmapMem<f32>, _>::from_mmap_file(&path, 0, [3]).unwrap();
+        let owned = m.to_owned_matrix();
+        drop(m);
+        std::fs::remove_file(&path).unwrap();
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 2., 3.], [3]);
+        assert_eq!((owned.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn file_too_short() {
+        let path = write_tmp("short", &[], &[1., 2.]);
+        let m = Matrix::<MmapMem<f32>, _>::from_mmap_file(&path, 0, [3]);
+        assert!(m.is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn misaligned_offset() {
+        let path = write_tmp("align", &[0; 3], &[1., 2.]);
+        let m = Matrix::<MmapMem<f32>, _>::from_mmap_file(&path, 3, [2]);
+        assert!(m.is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
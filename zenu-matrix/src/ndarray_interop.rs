@@ -0,0 +1,216 @@
+//! Conversions between zenu matrices and [`ndarray`] arrays.
+//!
+//! Enabled with the `ndarray` feature.
+//!
+//! View conversions never copy: the ndarray view shares the memory, shape and strides of
+//! the matrix and vice versa. Because a zenu view has to borrow an [`OwnedMem`], views of
+//! ndarray memory are only available inside a closure ([`with_matrix_view`],
+//! [`with_matrix_view_mut`]). Owned conversions copy into a default stride buffer.
+//!
+//! ```
+//! use ndarray::{array, ArrayD};
+//! use zenu_matrix::{
+//!     matrix::{IndexItem, OwnedMatrix, ToViewMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     ndarray_interop::{with_matrix_view, ToNdarray},
+//!     operation::transpose::TransposeInplace,
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+//! let t = x.transepose_by_index(&[1, 0]);
+//! let view = t.to_ndarray_view();
+//! assert_eq!(view, array![[1., 4.], [2., 5.], [3., 6.]].into_dyn());
+//!
+//! let a = array![[1., 2.], [3., 4.]];
+//! let sum = with_matrix_view(a.t(), |m| m.index_item([0, 1]) + m.index_item([1, 0]));
+//! assert_eq!(sum, 5.);
+//!
+//! let owned: ArrayD<f64> = x.into();
+//! assert_eq!(owned.shape(), &[2, 3]);
+//! ```
+use std::ptr::NonNull;
+
+use ndarray::{ArrayBase, ArrayD, ArrayView, ArrayViewD, ArrayViewMut, ArrayViewMutD, Dimension};
+use ndarray::{IxDyn, OwnedRepr, ShapeBuilder};
+
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{AsMutPtr, AsPtr, MatrixBase, OwnedMatrix, ToViewMatrix, ToViewMutMatrix},
+    matrix_impl::Matrix,
+    memory::{ToViewMemory, ToViewMutMemory},
+    memory_impl::{OwnedMem, ViewMem, ViewMutMem},
+    num::Num,
+};
+
+pub trait ToNdarray<T: Num> {
+    /// Zero-copy ndarray view with the same shape and strides.
+    fn to_ndarray_view(&self) -> ArrayViewD<'_, T>;
+    /// Copies the matrix into an owned ndarray array in standard layout.
+    fn to_ndarray(&self) -> ArrayD<T>;
+}
+
+pub trait ToNdarrayMut<T: Num> {
+    /// Zero-copy mutable ndarray view with the same shape and strides.
+    fn to_ndarray_view_mut(&mut self) -> ArrayViewMutD<'_, T>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> ToNdarray<T> for Matrix<M, DimDyn> {
+    fn to_ndarray_view(&self) -> ArrayViewD<'_, T> {
+        let shape = IxDyn(self.shape().slice()).strides(IxDyn(self.stride().slice()));
+        unsafe { ArrayViewD::from_shape_ptr(shape, self.to_view().as_ptr()) }
+    }
+
+    fn to_ndarray(&self) -> ArrayD<T> {
+        self.to_ndarray_view().to_owned()
+    }
+}
+
+impl<T: Num, M: ToViewMutMemory<Item = T>> ToNdarrayMut<T> for Matrix<M, DimDyn> {
+    fn to_ndarray_view_mut(&mut self) -> ArrayViewMutD<'_, T> {
+        let shape = IxDyn(self.shape().slice()).strides(IxDyn(self.stride().slice()));
+        unsafe { ArrayViewMutD::from_shape_ptr(shape, self.to_view_mut().as_mut_ptr()) }
+    }
+}
+
+/// ndarrayのshapeとstrideをzenuのものに変換し、参照される範囲の要素数を返す
+fn shape_stride_span(shape: &[usize], strides: &[isize]) -> (DimDyn, DimDyn, usize) {
+    assert!(
+        shape.len() <= 6,
+        "zenu matrices support at most 6 dimensions, got {}",
+        shape.len()
+    );
+    let mut zenu_shape = DimDyn::default();
+    let mut zenu_stride = DimDyn::default();
+    for (&sh, &st) in shape.iter().zip(strides) {
+        assert!(st >= 0, "negative strides are not supported");
+        zenu_shape.push_dim(sh);
+        zenu_stride.push_dim(st as usize);
+    }
+    let span = if zenu_shape.num_elm() == 0 {
+        0
+    } else {
+        zenu_shape
+            .into_iter()
+            .zip(zenu_stride)
+            .map(|(sh, st)| (sh - 1) * st)
+            .sum::<usize>()
+            + 1
+    };
+    (zenu_shape, zenu_stride, span)
+}
+
+/// Runs `f` with a zero-copy matrix view of an ndarray view.
+///
+/// Panics if the array has negative strides or more than 6 dimensions.
+pub fn with_matrix_view<T, D, F, R>(array: ArrayView<T, D>, f: F) -> R
+where
+    T: Num,
+    D: Dimension,
+    F: FnOnce(Matrix<ViewMem<T>, DimDyn>) -> R,
+{
+    let (shape, stride, span) = shape_stride_span(array.shape(), array.strides());
+    let ptr = NonNull::new(array.as_ptr() as *mut T).unwrap();
+    let memory = unsafe { OwnedMem::from_raw_parts_borrowed(ptr, span) };
+    f(Matrix::new(memory.to_view(0), shape, stride))
+}
+
+/// Runs `f` with a zero-copy mutable matrix view of an ndarray view.
+///
+/// Panics if the array has negative strides or more than 6 dimensions.
+pub fn with_matrix_view_mut<T, D, F, R>(mut array: ArrayViewMut<T, D>, f: F) -> R
+where
+    T: Num,
+    D: Dimension,
+    F: FnOnce(Matrix<ViewMutMem<T>, DimDyn>) -> R,
+{
+    let (shape, stride, span) = shape_stride_span(array.shape(), array.strides());
+    let ptr = NonNull::new(array.as_mut_ptr()).unwrap();
+    let mut memory = unsafe { OwnedMem::from_raw_parts_borrowed(ptr, span) };
+    f(Matrix::new(memory.to_view_mut(0), shape, stride))
+}
+
+impl<T: Num> From<Matrix<OwnedMem<T>, DimDyn>> for ArrayD<T> {
+    fn from(matrix: Matrix<OwnedMem<T>, DimDyn>) -> Self {
+        matrix.to_ndarray()
+    }
+}
+
+impl<T: Num, D: Dimension> From<ArrayBase<OwnedRepr<T>, D>> for Matrix<OwnedMem<T>, DimDyn> {
+    fn from(array: ArrayBase<OwnedRepr<T>, D>) -> Self {
+        Self::from_ndarray(array.view())
+    }
+}
+
+impl<T: Num> Matrix<OwnedMem<T>, DimDyn> {
+    /// Copies any ndarray view, including ones with negative strides, into a new default
+    /// stride matrix.
+    pub fn from_ndarray<D: Dimension>(array: ArrayView<T, D>) -> Self {
+        let shape = DimDyn::from(array.shape());
+        let vec = array.iter().copied().collect();
+        Matrix::from_vec(vec, shape)
+    }
+}
+
+#[cfg(test)]
+mod ndarray_interop {
+    use ndarray::{array, s, ArrayD, Axis};
+
+    use crate::{
+        dim::DimTrait,
+        matrix::{AsPtr, IndexItem, MatrixBase, MatrixSliceDyn, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{asum::Asum, basic_operations::MatrixAddAssign, transpose::TransposeInplace},
+        slice_dynamic,
+    };
+
+    use super::{with_matrix_view, with_matrix_view_mut, ToNdarray, ToNdarrayMut};
+
+    #[test]
+    fn matrix_to_ndarray_view_strided() {
+        let x = OwnedMatrixDyn::from_vec((0..12).map(|x| x as f64).collect(), [3, 4]);
+        let sliced = x.slice_dyn(slice_dynamic!(.., 1..;2));
+        let view = sliced.to_ndarray_view();
+        assert_eq!(view.shape(), &[3, 2]);
+        assert_eq!(view, array![[1., 3.], [5., 7.], [9., 11.]].into_dyn());
+        assert_eq!(view.as_ptr(), sliced.as_ptr());
+    }
+
+    #[test]
+    fn matrix_to_ndarray_view_mut() {
+        let mut x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        x.to_ndarray_view_mut().index_axis_mut(Axis(0), 1).fill(0.);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 2., 0., 0.], [2, 2]);
+        assert_eq!((x.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn ndarray_view_to_matrix_transposed() {
+        let a = array![[1., 2., 3.], [4., 5., 6.]];
+        let t = a.t();
+        with_matrix_view(t, |m| {
+            assert_eq!(m.shape().slice(), [3, 2]);
+            assert_eq!(m.stride().slice(), [1, 3]);
+            assert_eq!(m.index_item([2, 0]), 3.);
+            assert_eq!(m.index_item([0, 1]), 4.);
+        });
+    }
+
+    #[test]
+    fn ndarray_view_mut_to_matrix() {
+        let mut a = array![[1., 2.], [3., 4.]];
+        let one = OwnedMatrixDyn::from_vec(vec![1., 1.], [2]);
+        with_matrix_view_mut(a.slice_mut(s![.., 1]), |mut m| m.add_assign(one.to_view()));
+        assert_eq!(a, array![[1., 3.], [3., 5.]]);
+    }
+
+    #[test]
+    fn owned_roundtrip() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let t = x.transepose_by_index(&[1, 0]).to_ndarray();
+        assert_eq!(t, array![[1., 4.], [2., 5.], [3., 6.]].into_dyn());
+
+        let a: ArrayD<f64> = x.clone().into();
+        let back = OwnedMatrixDyn::from(a);
+        assert_eq!((back.to_view() - x.to_view()).asum(), 0.);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, ToViewMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::zip_lanes,
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+pub trait Cross<T: Num> {
+    /// Cross product of the 3-vectors along `axis`.
+    ///
+    /// Both matrices must have the same shape and `axis` must have length 3.
+    fn cross<N, E>(&self, other: &Matrix<N, E>, axis: usize) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        N: ToViewMemory<Item = T>,
+        E: DimTrait;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>, D: DimTrait> Cross<T> for Matrix<M, D> {
+    fn cross<N, E>(&self, other: &Matrix<N, E>, axis: usize) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        N: ToViewMemory<Item = T>,
+        E: DimTrait,
+    {
+        let a = self.to_view().into_dyn_dim();
+        let b = other.to_view().into_dyn_dim();
+        assert!(
+            axis < a.shape().len() && a.shape()[axis] == 3,
+            "cross needs an axis of length 3, got shape {:?} and axis {}",
+            a.shape().slice(),
+            axis
+        );
+        zip_lanes(&a, &b, axis, 3, |a, b, out| {
+            out[0] = a[1] * b[2] - a[2] * b[1];
+            out[1] = a[2] * b[0] - a[0] * b[2];
+            out[2] = a[0] * b[1] - a[1] * b[0];
+        })
+    }
+}
+
+#[cfg(test)]
+mod cross {
+    use crate::{
+        matrix::{OwnedMatrix, ToViewMatrix},
+        matrix_impl::{OwnedMatrix1D, OwnedMatrixDyn},
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::Cross;
+
+    #[test]
+    fn cross_unit_vectors() {
+        let x = OwnedMatrix1D::from_vec(vec![1., 0., 0.], [3]);
+        let y = OwnedMatrix1D::from_vec(vec![0., 1., 0.], [3]);
+        let z = x.cross(&y, 0);
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 0., 1.], [3]);
+        assert_eq!((z.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn cross_along_axis() {
+        // two vectors stored as columns: [1, 2, 3] and [4, 5, 6]
+        let a = OwnedMatrixDyn::from_vec(vec![1., 4., 2., 5., 3., 6.], [3, 2]);
+        let b = OwnedMatrixDyn::from_vec(vec![4., 1., 5., 2., 6., 3.], [3, 2]);
+        let c = a.cross(&b, 0);
+        let ans = OwnedMatrixDyn::from_vec(vec![-3., 3., 6., -6., -3., 3.], [3, 2]);
+        assert_eq!((c.to_view() - ans.to_view()).asum(), 0.);
+
+        // the same vectors as rows of transposed views
+        let c = a
+            .transepose_by_index(&[1, 0])
+            .cross(&b.transepose_by_index(&[1, 0]), 1);
+        let ans = OwnedMatrixDyn::from_vec(vec![-3., 6., -3., 3., -6., 3.], [2, 3]);
+        assert_eq!((c.to_view() - ans.to_view()).asum(), 0.);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::MatrixBase,
+    matrix_impl::Matrix,
+    matrix_iter::map_lanes,
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+pub trait Cumulative<T: Num> {
+    /// Running sum along `axis`. The result has the same shape as `self`.
+    fn cumsum(&self, axis: usize) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Running product along `axis`. The result has the same shape as `self`.
+    fn cumprod(&self, axis: usize) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Cumulative<T> for Matrix<M, DimDyn> {
+    fn cumsum(&self, axis: usize) -> Matrix<OwnedMem<T>, DimDyn> {
+        scan(self, axis, |acc, x| acc + x)
+    }
+
+    fn cumprod(&self, axis: usize) -> Matrix<OwnedMem<T>, DimDyn> {
+        scan(self, axis, |acc, x| acc * x)
+    }
+}
+
+fn scan<T, M, F>(x: &Matrix<M, DimDyn>, axis: usize, f: F) -> Matrix<OwnedMem<T>, DimDyn>
+where
+    T: Num,
+    M: ToViewMemory<Item = T>,
+    F: Fn(T, T) -> T,
+{
+    let len = x.shape().slice().get(axis).copied().unwrap_or(0);
+    map_lanes(x, axis, len, |lane, out| {
+        let mut acc = None;
+        for (o, &v) in out.iter_mut().zip(lane) {
+            let next = match acc {
+                Some(acc) => f(acc, v),
+                None => v,
+            };
+            *o = next;
+            acc = Some(next);
+        }
+    })
+}
+
+#[cfg(test)]
+mod cumulative {
+    use crate::{
+        matrix::{OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::Cumulative;
+
+    #[test]
+    fn cumsum_1d() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [4]);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 3., 6., 10.], [4]);
+        assert_eq!((x.cumsum(0).to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn cumsum_2d() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 5., 7., 9.], [2, 3]);
+        assert_eq!((x.cumsum(0).to_view() - ans.to_view()).asum(), 0.);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 3., 6., 4., 9., 15.], [2, 3]);
+        assert_eq!((x.cumsum(1).to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn cumprod_view() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        // [[1, 4], [2, 5], [3, 6]]
+        let t = x.transepose_by_index(&[1, 0]);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 4., 2., 20., 6., 120.], [3, 2]);
+        assert_eq!((t.cumprod(0).to_view() - ans.to_view()).asum(), 0.);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, MatrixSliceDyn, OwnedMatrix, ToViewMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::MatrixElementIter,
+    memory::ToViewMemory,
+    memory_impl::{OwnedMem, ViewMem},
+    num::Num,
+    shape_stride::ShapeStride,
+    slice::{dynamic::Slice, slice_dim::SliceDim},
+};
+
+use super::to_default_stride::ToDefaultStride;
+
+pub trait Diag<T: Num> {
+    /// A 1-D matrix becomes a square matrix with it on the main diagonal; a 2-D matrix
+    /// gives a copy of its main diagonal.
+    fn diag(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// `[..., n]` -> `[..., n, n]`, putting the last axis on the diagonal of the last two.
+    fn diag_embed(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Zero-copy view of the diagonal of the last two axes, `[..., rows, cols]` ->
+    /// `[..., k]`.
+    ///
+    /// `offset > 0` selects a diagonal above the main one and `offset < 0` one below.
+    fn diagonal(&self, offset: isize) -> Matrix<ViewMem<T>, DimDyn>;
+    /// Sum of the main diagonal of a 2-D matrix.
+    fn trace(&self) -> T;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>, D: DimTrait> Diag<T> for Matrix<M, D> {
+    fn diag(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        match self.shape().len() {
+            1 => self.diag_embed(),
+            2 => self.diagonal(0).to_default_stride(),
+            ndim => panic!("diag needs a 1-D or 2-D matrix, got {}-D", ndim),
+        }
+    }
+
+    fn diag_embed(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        let shape = DimDyn::from(self.shape().slice());
+        assert!(!shape.is_empty(), "diag_embed needs at least 1 dimension");
+        let n = shape[shape.len() - 1];
+        let mut out_shape = shape;
+        out_shape.push_dim(n);
+
+        let mut out = vec![T::zero(); out_shape.num_elm()];
+        let values = self.to_view().into_dyn_dim().iter().collect::<Vec<_>>();
+        if n > 0 {
+            for (batch, lane) in values.chunks(n).enumerate() {
+                for (i, &v) in lane.iter().enumerate() {
+                    out[batch * n * n + i * n + i] = v;
+                }
+            }
+        }
+        Matrix::from_vec(out, out_shape)
+    }
+
+    fn diagonal(&self, offset: isize) -> Matrix<ViewMem<T>, DimDyn> {
+        let shape = DimDyn::from(self.shape().slice());
+        let ndim = shape.len();
+        assert!(
+            ndim >= 2,
+            "diagonal needs at least 2 dimensions, got {}",
+            ndim
+        );
+        let rows = shape[ndim - 2];
+        let cols = shape[ndim - 1];
+        let (row_start, col_start) = if offset >= 0 {
+            (0, offset as usize)
+        } else {
+            (offset.unsigned_abs(), 0)
+        };
+        let len = rows
+            .saturating_sub(row_start)
+            .min(cols.saturating_sub(col_start));
+
+        // 対角成分の先頭までずらしたview
+        let mut view = if len > 0 {
+            let mut index = vec![SliceDim::from(..); ndim];
+            index[ndim - 2] = SliceDim::from(row_start..);
+            index[ndim - 1] = SliceDim::from(col_start..);
+            self.slice_dyn(Slice::from(index.as_slice()))
+        } else {
+            self.to_view().into_dyn_dim()
+        };
+
+        let stride = view.stride();
+        let mut new_shape = DimDyn::default();
+        let mut new_stride = DimDyn::default();
+        for k in 0..ndim - 2 {
+            new_shape.push_dim(shape[k]);
+            new_stride.push_dim(stride[k]);
+        }
+        new_shape.push_dim(len);
+        new_stride.push_dim(stride[ndim - 2] + stride[ndim - 1]);
+        view.update_shape_stride(ShapeStride::new(new_shape, new_stride));
+        view
+    }
+
+    fn trace(&self) -> T {
+        assert_eq!(
+            self.shape().len(),
+            2,
+            "trace needs a 2-D matrix, got {}-D",
+            self.shape().len()
+        );
+        self.diagonal(0).iter().fold(T::zero(), |acc, x| acc + x)
+    }
+}
+
+#[cfg(test)]
+mod diag {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::{OwnedMatrix2D, OwnedMatrixDyn},
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::Diag;
+
+    fn arange(shape: &[usize]) -> OwnedMatrixDyn<f64> {
+        let n = shape.iter().product::<usize>();
+        OwnedMatrixDyn::from_vec((0..n).map(|x| x as f64).collect(), shape)
+    }
+
+    #[test]
+    fn diag_1d_and_2d() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        let d = x.diag();
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 0., 0., 2.], [2, 2]);
+        assert_eq!((d.to_view() - ans.to_view()).asum(), 0.);
+
+        let x = OwnedMatrix2D::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let d = x.diag();
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 5.], [2]);
+        assert_eq!((d.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn diag_embed_batched() {
+        let x = arange(&[2, 2]);
+        let d = x.diag_embed();
+        assert_eq!(d.shape().slice(), [2, 2, 2]);
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 0., 0., 1., 2., 0., 0., 3.], [2, 2, 2]);
+        assert_eq!((d.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn diagonal_offsets() {
+        // [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]
+        let x = arange(&[3, 4]);
+        let d = x.diagonal(0);
+        assert_eq!(d.shape().slice(), [3]);
+        assert_eq!(d.index_item([2]), 10.);
+
+        let d = x.diagonal(2);
+        assert_eq!(d.shape().slice(), [2]);
+        assert_eq!(d.index_item([0]), 2.);
+        assert_eq!(d.index_item([1]), 7.);
+
+        let d = x.diagonal(-1);
+        assert_eq!(d.shape().slice(), [2]);
+        assert_eq!(d.index_item([0]), 4.);
+        assert_eq!(d.index_item([1]), 9.);
+
+        assert_eq!(x.diagonal(4).shape().slice(), [0]);
+        assert_eq!(x.diagonal(-3).shape().slice(), [0]);
+    }
+
+    #[test]
+    fn diagonal_batched_view() {
+        let x = arange(&[2, 3, 3]);
+        let t = x.transepose_by_index(&[0, 2, 1]);
+        let d = t.diagonal(1);
+        assert_eq!(d.shape().slice(), [2, 2]);
+        // t[1] = x[1]^T, so the first super diagonal of t is the first sub diagonal of x[1]
+        assert_eq!(d.index_item([1, 0]), x.index_item([1, 1, 0]));
+        assert_eq!(d.index_item([1, 1]), x.index_item([1, 2, 1]));
+    }
+
+    #[test]
+    fn trace() {
+        let x = arange(&[3, 3]);
+        assert_eq!(x.trace(), 12.);
+        let x = OwnedMatrix2D::from_vec(vec![1., 2., 3., 4., 5., 6.], [3, 2]);
+        assert_eq!(x.trace(), 5.);
+    }
+}
@@ -0,0 +1,581 @@
+//! Discrete Fourier transforms along an arbitrary axis of a matrix.
+//!
+//! Complex values are stored as a pair of real matrices of identical shape
+//! ([`ComplexMatrix`]), the layout every kernel in this module works on. When a single
+//! buffer is more convenient, [`ComplexMatrix::to_interleaved`] packs the pair into one
+//! matrix with a trailing axis of length 2 (`[..., 2]`, real part first), and
+//! [`ComplexMatrix::from_interleaved`] unpacks it again.
+//!
+//! Lengths that are a power of two use an iterative radix-2 Cooley-Tukey transform;
+//! every other length goes through Bluestein's algorithm, so all transforms are
+//! `O(n log n)`.
+//!
+//! Normalization follows numpy: the forward transforms are unscaled and the inverse
+//! transforms divide by `n`.
+//!
+//! # Examples
+//!
+//! ```
+//! use zenu_matrix::{
+//!     matrix::{OwnedMatrix, ToViewMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     operation::{asum::Asum, fft::Fft},
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [4]);
+//! let spectrum = x.fft(0);
+//! let ans_re = OwnedMatrixDyn::from_vec(vec![10., -2., -2., -2.], [4]);
+//! let ans_im = OwnedMatrixDyn::from_vec(vec![0., 2., 0., -2.], [4]);
+//! assert!((spectrum.re.to_view() - ans_re.to_view()).asum() < 1e-10);
+//! assert!((spectrum.im.to_view() - ans_im.to_view()).asum() < 1e-10);
+//!
+//! let restored = spectrum.ifft(0);
+//! assert!((restored.re.to_view() - x.to_view()).asum() < 1e-10);
+//! ```
+use crate::{
+    constructor::zeros::Zeros,
+    dim::{DimDyn, DimTrait},
+    index::index_dyn_impl::Index,
+    matrix::{
+        IndexAxisDyn, IndexAxisMutDyn, MatrixBase, OwnedMatrix, ToViewMatrix, ToViewMutMatrix,
+    },
+    matrix_impl::Matrix,
+    matrix_iter::{axis_to_last_perm, gather_lanes, restore_lanes, MatrixElementIter},
+    memory::ToViewMemory,
+    memory_impl::{OwnedMem, ViewMem},
+    num::Num,
+};
+
+use super::{copy_from::CopyFrom, to_default_stride::ToDefaultStride};
+
+/// A complex valued matrix stored as separate real and imaginary parts.
+#[derive(Clone)]
+pub struct ComplexMatrix<T: Num> {
+    pub re: Matrix<OwnedMem<T>, DimDyn>,
+    pub im: Matrix<OwnedMem<T>, DimDyn>,
+}
+
+impl<T: Num> ComplexMatrix<T> {
+    pub fn new(re: Matrix<OwnedMem<T>, DimDyn>, im: Matrix<OwnedMem<T>, DimDyn>) -> Self {
+        assert_eq!(
+            re.shape(),
+            im.shape(),
+            "real and imaginary parts must have the same shape"
+        );
+        Self { re, im }
+    }
+
+    /// Builds a complex matrix with a zero imaginary part.
+    pub fn from_real<M: ToViewMemory<Item = T>>(re: &Matrix<M, DimDyn>) -> Self {
+        let re = re.to_view().to_default_stride();
+        let im = Matrix::<OwnedMem<T>, DimDyn>::zeros(re.shape());
+        Self { re, im }
+    }
+
+    /// Unpacks a matrix whose last axis holds `[re, im]` pairs.
+    pub fn from_interleaved<M: ToViewMemory<Item = T>>(interleaved: &Matrix<M, DimDyn>) -> Self {
+        let shape = interleaved.shape();
+        assert!(
+            !shape.is_empty() && shape[shape.len() - 1] == 2,
+            "interleaved complex matrix must have a last axis of length 2"
+        );
+        let last = shape.len() - 1;
+        let view = interleaved.to_view();
+        Self {
+            re: view.index_axis_dyn(Index::new(last, 0)).to_default_stride(),
+            im: view.index_axis_dyn(Index::new(last, 1)).to_default_stride(),
+        }
+    }
+
+    /// Packs the pair into one matrix with a trailing axis of length 2.
+    pub fn to_interleaved(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        let mut shape = self.shape();
+        shape.push_dim(2);
+        let mut out = Matrix::<OwnedMem<T>, DimDyn>::zeros(shape);
+        let last = shape.len() - 1;
+        out.to_view_mut()
+            .index_axis_mut_dyn(Index::new(last, 0))
+            .copy_from(&self.re.to_view());
+        out.to_view_mut()
+            .index_axis_mut_dyn(Index::new(last, 1))
+            .copy_from(&self.im.to_view());
+        out
+    }
+
+    pub fn shape(&self) -> DimDyn {
+        self.re.shape()
+    }
+
+    /// Element wise magnitude `sqrt(re^2 + im^2)`.
+    pub fn abs(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        let re = self.re.iter().collect::<Vec<_>>();
+        let im = self.im.iter().collect::<Vec<_>>();
+        let abs = re.iter().zip(im.iter()).map(|(r, i)| r.hypot(*i)).collect();
+        Matrix::from_vec(abs, self.shape())
+    }
+
+    /// Complex to complex forward transform along `axis`.
+    pub fn fft(&self, axis: usize) -> ComplexMatrix<T> {
+        let n = axis_len(self.shape(), axis);
+        apply_along_axis(
+            &self.re,
+            Some(&self.im),
+            axis,
+            n,
+            |re, im, out_re, out_im| {
+                out_re.copy_from_slice(re);
+                out_im.copy_from_slice(im);
+                fft_kernel_cpu(out_re, out_im, false);
+            },
+        )
+    }
+
+    /// Complex to complex inverse transform along `axis`, scaled by `1 / n`.
+    pub fn ifft(&self, axis: usize) -> ComplexMatrix<T> {
+        let n = axis_len(self.shape(), axis);
+        apply_along_axis(
+            &self.re,
+            Some(&self.im),
+            axis,
+            n,
+            |re, im, out_re, out_im| {
+                out_re.copy_from_slice(re);
+                out_im.copy_from_slice(im);
+                fft_kernel_cpu(out_re, out_im, true);
+                scale(out_re, out_im, n);
+            },
+        )
+    }
+
+    /// Inverse of [`Fft::rfft`].
+    ///
+    /// `self` holds the non-negative frequency half of a Hermitian spectrum along `axis`.
+    /// `n` is the length of the real output, defaulting to `2 * (m - 1)` where `m` is the
+    /// length of `axis`. Missing frequencies are treated as zero and surplus ones are
+    /// dropped.
+    pub fn irfft(&self, axis: usize, n: Option<usize>) -> Matrix<OwnedMem<T>, DimDyn> {
+        let m = axis_len(self.shape(), axis);
+        let n = n.unwrap_or(2 * (m.max(1) - 1));
+        assert!(n > 0, "irfft output length must be greater than zero");
+        let half = n / 2 + 1;
+        let mut full_re = vec![T::zero(); n];
+        let mut full_im = vec![T::zero(); n];
+        apply_along_axis(&self.re, Some(&self.im), axis, n, |re, im, out_re, _| {
+            let used = half.min(m);
+            full_re[..used].copy_from_slice(&re[..used]);
+            full_im[..used].copy_from_slice(&im[..used]);
+            for v in full_re[used..].iter_mut().chain(full_im[used..].iter_mut()) {
+                *v = T::zero();
+            }
+            for k in 1..half {
+                if n - k >= half {
+                    full_re[n - k] = full_re[k];
+                    full_im[n - k] = -full_im[k];
+                }
+            }
+            fft_kernel_cpu(&mut full_re, &mut full_im, true);
+            let n_t = T::from_usize(n);
+            for (o, v) in out_re.iter_mut().zip(full_re.iter()) {
+                *o = *v / n_t;
+            }
+        })
+        .re
+    }
+}
+
+pub trait Fft<T: Num> {
+    /// Forward transform of a real matrix along `axis`.
+    fn fft(&self, axis: usize) -> ComplexMatrix<T>;
+    /// Forward transform of a real matrix along `axis`, keeping only the `n / 2 + 1`
+    /// non-negative frequencies.
+    fn rfft(&self, axis: usize) -> ComplexMatrix<T>;
+    /// Short-time Fourier transform along the last axis.
+    ///
+    /// The signal is cut into frames of `n_fft` samples every `hop_length` samples, each
+    /// frame is multiplied by `window` (a rectangular window when `None`) and transformed
+    /// with [`Fft::rfft`]. Frames are not padded, so only frames that fit entirely inside
+    /// the signal are produced.
+    ///
+    /// An input of shape `[..., len]` gives an output of shape
+    /// `[..., 1 + (len - n_fft) / hop_length, n_fft / 2 + 1]`.
+    fn stft(
+        &self,
+        n_fft: usize,
+        hop_length: usize,
+        window: Option<Matrix<ViewMem<T>, DimDyn>>,
+    ) -> ComplexMatrix<T>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Fft<T> for Matrix<M, DimDyn> {
+    fn fft(&self, axis: usize) -> ComplexMatrix<T> {
+        let n = axis_len(self.shape(), axis);
+        apply_along_axis(self, None, axis, n, |re, _, out_re, out_im| {
+            out_re.copy_from_slice(re);
+            fft_kernel_cpu(out_re, out_im, false);
+        })
+    }
+
+    fn rfft(&self, axis: usize) -> ComplexMatrix<T> {
+        let n = axis_len(self.shape(), axis);
+        let mut buf_re = vec![T::zero(); n];
+        let mut buf_im = vec![T::zero(); n];
+        apply_along_axis(self, None, axis, n / 2 + 1, |re, _, out_re, out_im| {
+            buf_re.copy_from_slice(re);
+            buf_im.iter_mut().for_each(|v| *v = T::zero());
+            fft_kernel_cpu(&mut buf_re, &mut buf_im, false);
+            out_re.copy_from_slice(&buf_re[..out_re.len()]);
+            out_im.copy_from_slice(&buf_im[..out_im.len()]);
+        })
+    }
+
+    fn stft(
+        &self,
+        n_fft: usize,
+        hop_length: usize,
+        window: Option<Matrix<ViewMem<T>, DimDyn>>,
+    ) -> ComplexMatrix<T> {
+        let shape = self.shape();
+        assert!(!shape.is_empty(), "stft is not defined for scalars");
+        assert!(n_fft > 0, "n_fft must be greater than zero");
+        assert!(hop_length > 0, "hop_length must be greater than zero");
+        let len = shape[shape.len() - 1];
+        assert!(
+            len >= n_fft,
+            "signal length {} is shorter than n_fft {}",
+            len,
+            n_fft
+        );
+        let window = match window {
+            Some(window) => {
+                assert_eq!(
+                    window.shape().slice(),
+                    &[n_fft],
+                    "window must be a 1-D matrix of length n_fft"
+                );
+                window.iter().collect()
+            }
+            None => vec![T::one(); n_fft],
+        };
+
+        let num_frames = 1 + (len - n_fft) / hop_length;
+        let mut frames_shape = DimDyn::default();
+        for &s in &shape.slice()[..shape.len() - 1] {
+            frames_shape.push_dim(s);
+        }
+        frames_shape.push_dim(num_frames);
+        frames_shape.push_dim(n_fft);
+
+        let signal = self.iter().collect::<Vec<_>>();
+        let mut frames = Vec::with_capacity(frames_shape.num_elm());
+        for lane in signal.chunks(len) {
+            for f in 0..num_frames {
+                let start = f * hop_length;
+                frames.extend(
+                    lane[start..start + n_fft]
+                        .iter()
+                        .zip(window.iter())
+                        .map(|(x, w)| *x * *w),
+                );
+            }
+        }
+        let frames = Matrix::<OwnedMem<T>, DimDyn>::from_vec(frames, frames_shape);
+        frames.rfft(frames_shape.len() - 1)
+    }
+}
+
+/// Periodic Hann window of length `n`, the usual window for [`Fft::stft`].
+pub fn hann_window<T: Num>(n: usize) -> Matrix<OwnedMem<T>, DimDyn> {
+    let window = (0..n)
+        .map(|i| {
+            let phase = 2. * std::f64::consts::PI * i as f64 / n as f64;
+            T::from(0.5 - 0.5 * phase.cos()).unwrap()
+        })
+        .collect();
+    Matrix::from_vec(window, [n])
+}
+
+fn axis_len(shape: DimDyn, axis: usize) -> usize {
+    assert!(
+        axis < shape.len(),
+        "axis {} is out of range for a {}-D matrix",
+        axis,
+        shape.len()
+    );
+    shape[axis]
+}
+
+/// Runs `kernel` on every 1-D lane along `axis`.
+///
+/// `kernel` receives the input lane (`re`, `im`) and writes an output lane of length
+/// `out_len`. The imaginary input lane is all zeros when `im` is `None`.
+fn apply_along_axis<T, M, F>(
+    re: &Matrix<M, DimDyn>,
+    im: Option<&Matrix<OwnedMem<T>, DimDyn>>,
+    axis: usize,
+    out_len: usize,
+    mut kernel: F,
+) -> ComplexMatrix<T>
+where
+    T: Num,
+    M: ToViewMemory<Item = T>,
+    F: FnMut(&[T], &[T], &mut [T], &mut [T]),
+{
+    let shape = re.shape();
+    let n = axis_len(shape, axis);
+    let perm = axis_to_last_perm(shape, axis);
+
+    let in_re = gather_lanes(re, &perm);
+    let in_im = match im {
+        Some(im) => gather_lanes(im, &perm),
+        None => vec![T::zero(); in_re.len()],
+    };
+
+    let num_lanes = in_re.len().checked_div(n).unwrap_or(0);
+    let mut out_re = vec![T::zero(); num_lanes * out_len];
+    let mut out_im = vec![T::zero(); num_lanes * out_len];
+    if n > 0 && out_len > 0 {
+        for (((re, im), out_re), out_im) in in_re
+            .chunks(n)
+            .zip(in_im.chunks(n))
+            .zip(out_re.chunks_mut(out_len))
+            .zip(out_im.chunks_mut(out_len))
+        {
+            kernel(re, im, out_re, out_im);
+        }
+    }
+
+    ComplexMatrix {
+        re: restore_lanes(out_re, shape, &perm, out_len),
+        im: restore_lanes(out_im, shape, &perm, out_len),
+    }
+}
+
+fn scale<T: Num>(re: &mut [T], im: &mut [T], n: usize) {
+    let n = T::from_usize(n);
+    for v in re.iter_mut().chain(im.iter_mut()) {
+        *v /= n;
+    }
+}
+
+/// In-place unnormalized DFT of one lane.
+fn fft_kernel_cpu<T: Num>(re: &mut [T], im: &mut [T], inverse: bool) {
+    let n = re.len();
+    if n <= 1 {
+        return;
+    }
+    if n.is_power_of_two() {
+        radix2_kernel_cpu(re, im, inverse);
+    } else {
+        bluestein_kernel_cpu(re, im, inverse);
+    }
+}
+
+fn radix2_kernel_cpu<T: Num>(re: &mut [T], im: &mut [T], inverse: bool) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1. } else { -1. };
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        // twiddle factorはf64で計算して精度を保つ
+        let twiddles: Vec<(T, T)> = (0..half)
+            .map(|k| {
+                let angle = sign * 2. * std::f64::consts::PI * k as f64 / len as f64;
+                (T::from(angle.cos()).unwrap(), T::from(angle.sin()).unwrap())
+            })
+            .collect();
+        for start in (0..n).step_by(len) {
+            for (k, &(w_re, w_im)) in twiddles.iter().enumerate() {
+                let a = start + k;
+                let b = a + half;
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Bluestein's algorithm: rewrites a DFT of any length as a convolution that is
+/// evaluated with power of two transforms.
+fn bluestein_kernel_cpu<T: Num>(re: &mut [T], im: &mut [T], inverse: bool) {
+    let n = re.len();
+    let m = (2 * n - 1).next_power_of_two();
+    let sign = if inverse { 1. } else { -1. };
+
+    // chirp w_k = exp(sign * i * pi * k^2 / n)
+    // k^2 は 2n で割った余りを使って角度の桁落ちを防ぐ
+    let chirp: Vec<(T, T)> = (0..n)
+        .map(|k| {
+            let k2 = (k as u128 * k as u128 % (2 * n as u128)) as f64;
+            let angle = sign * std::f64::consts::PI * k2 / n as f64;
+            (T::from(angle.cos()).unwrap(), T::from(angle.sin()).unwrap())
+        })
+        .collect();
+
+    let mut a_re = vec![T::zero(); m];
+    let mut a_im = vec![T::zero(); m];
+    for k in 0..n {
+        let (w_re, w_im) = chirp[k];
+        a_re[k] = re[k] * w_re - im[k] * w_im;
+        a_im[k] = re[k] * w_im + im[k] * w_re;
+    }
+
+    let mut b_re = vec![T::zero(); m];
+    let mut b_im = vec![T::zero(); m];
+    b_re[0] = chirp[0].0;
+    b_im[0] = -chirp[0].1;
+    for k in 1..n {
+        b_re[k] = chirp[k].0;
+        b_im[k] = -chirp[k].1;
+        b_re[m - k] = chirp[k].0;
+        b_im[m - k] = -chirp[k].1;
+    }
+
+    radix2_kernel_cpu(&mut a_re, &mut a_im, false);
+    radix2_kernel_cpu(&mut b_re, &mut b_im, false);
+    for i in 0..m {
+        let r = a_re[i] * b_re[i] - a_im[i] * b_im[i];
+        let j = a_re[i] * b_im[i] + a_im[i] * b_re[i];
+        a_re[i] = r;
+        a_im[i] = j;
+    }
+    radix2_kernel_cpu(&mut a_re, &mut a_im, true);
+    let m_t = T::from_usize(m);
+
+    for k in 0..n {
+        let (w_re, w_im) = chirp[k];
+        let c_re = a_re[k] / m_t;
+        let c_im = a_im[k] / m_t;
+        re[k] = c_re * w_re - c_im * w_im;
+        im[k] = c_re * w_im + c_im * w_re;
+    }
+}
+
+#[cfg(test)]
+mod fft {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::asum::Asum,
+    };
+
+    use super::{hann_window, ComplexMatrix, Fft};
+
+    fn naive_dft(x: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let n = x.len();
+        let mut re = vec![0.; n];
+        let mut im = vec![0.; n];
+        for k in 0..n {
+            for (t, v) in x.iter().enumerate() {
+                let angle = -2. * std::f64::consts::PI * (k * t) as f64 / n as f64;
+                re[k] += v * angle.cos();
+                im[k] += v * angle.sin();
+            }
+        }
+        (re, im)
+    }
+
+    #[test]
+    fn fft_1d_power_of_two() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [4]);
+        let y = x.fft(0);
+        let ans_re = OwnedMatrixDyn::from_vec(vec![10., -2., -2., -2.], [4]);
+        let ans_im = OwnedMatrixDyn::from_vec(vec![0., 2., 0., -2.], [4]);
+        assert!((y.re.to_view() - ans_re.to_view()).asum() < 1e-10);
+        assert!((y.im.to_view() - ans_im.to_view()).asum() < 1e-10);
+    }
+
+    #[test]
+    fn fft_1d_non_power_of_two() {
+        let v = vec![0.5, -1., 2., 3.5, 0., 1.25, -0.75];
+        let x = OwnedMatrixDyn::from_vec(v.clone(), [7]);
+        let y = x.fft(0);
+        let (re, im) = naive_dft(&v);
+        let ans_re = OwnedMatrixDyn::from_vec(re, [7]);
+        let ans_im = OwnedMatrixDyn::from_vec(im, [7]);
+        assert!((y.re.to_view() - ans_re.to_view()).asum() < 1e-10);
+        assert!((y.im.to_view() - ans_im.to_view()).asum() < 1e-10);
+    }
+
+    #[test]
+    fn fft_2d_axis0() {
+        // [[1, 2, 3], [4, 5, 6]] along axis 0 -> [[5, 7, 9], [-3, -3, -3]]
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let y = x.fft(0);
+        let ans_re = OwnedMatrixDyn::from_vec(vec![5., 7., 9., -3., -3., -3.], [2, 3]);
+        assert!((y.re.to_view() - ans_re.to_view()).asum() < 1e-10);
+        assert!(y.im.to_view().asum() < 1e-10);
+    }
+
+    #[test]
+    fn fft_ifft_3d_roundtrip() {
+        let v = (0..30).map(|i| (i as f64 * 0.37).sin()).collect::<Vec<_>>();
+        let x = OwnedMatrixDyn::from_vec(v, [2, 5, 3]);
+        for axis in 0..3 {
+            let y = x.fft(axis).ifft(axis);
+            assert_eq!(y.shape(), x.shape());
+            assert!((y.re.to_view() - x.to_view()).asum() < 1e-10);
+            assert!(y.im.to_view().asum() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn rfft_irfft_roundtrip() {
+        for n in [6, 7] {
+            let v = (0..n).map(|i| (i as f64).cos() + 0.1 * i as f64).collect();
+            let x = OwnedMatrixDyn::from_vec(v, [n]);
+            let y = x.rfft(0);
+            assert_eq!(y.shape().slice(), &[n / 2 + 1]);
+            let full = x.fft(0);
+            for k in 0..n / 2 + 1 {
+                let diff = y.re.index_item([k]) - full.re.index_item([k]);
+                assert!(diff.abs() < 1e-10);
+            }
+            let restored = y.irfft(0, Some(n));
+            assert!((restored.to_view() - x.to_view()).asum() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn interleaved_roundtrip() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let y = x.fft(1);
+        let packed = y.to_interleaved();
+        assert_eq!(packed.shape().slice(), &[2, 3, 2]);
+        let unpacked = ComplexMatrix::from_interleaved(&packed);
+        assert!((unpacked.re.to_view() - y.re.to_view()).asum() < 1e-12);
+        assert!((unpacked.im.to_view() - y.im.to_view()).asum() < 1e-12);
+    }
+
+    #[test]
+    fn stft_frames() {
+        let v = (0..10).map(|i| i as f64).collect::<Vec<_>>();
+        let x = OwnedMatrixDyn::from_vec(v.clone(), [10]);
+        let window = hann_window::<f64>(4);
+        let y = x.stft(4, 3, Some(window.to_view()));
+        assert_eq!(y.shape().slice(), &[3, 3]);
+
+        let frame = (0..4)
+            .map(|i| v[3 + i] * window.index_item([i]))
+            .collect::<Vec<_>>();
+        let (re, im) = naive_dft(&frame);
+        for k in 0..3 {
+            assert!((y.re.index_item([1, k]) - re[k]).abs() < 1e-10);
+            assert!((y.im.index_item([1, k]) - im[k]).abs() < 1e-10);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! NaN / infinity detection.
+//!
+//! Masks are matrices of the same element type holding `1` where the condition holds
+//! and `0` elsewhere, so they compose with the arithmetic operations and with
+//! [`AnyAll`], which treats every non-zero element as true.
+//!
+//! [`NonFinite::has_non_finite`] is a cheap check meant for debug assertions:
+//!
+//! ```
+//! use zenu_matrix::{
+//!     matrix::{IndexItem, OwnedMatrix, ToViewMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     operation::finite::{AnyAll, NonFinite},
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec(vec![1., f64::NAN, f64::INFINITY, 4.], [2, 2]);
+//! assert!(x.has_non_finite());
+//! assert_eq!(x.is_nan().any(None, false).index_item([]), 1.);
+//!
+//! let cleaned = x.nan_to_num(0., None, None);
+//! debug_assert!(!cleaned.to_view().has_non_finite());
+//! ```
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{AsPtr, MatrixBase, ToViewMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::{reduce_lanes, MatrixElementIter},
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+pub trait NonFinite<T: Num> {
+    /// `1` where the element is NaN.
+    fn is_nan(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// `1` where the element is positive or negative infinity.
+    fn is_inf(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// `1` where the element is neither NaN nor infinite.
+    fn is_finite(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Replaces NaN with `nan`, positive infinity with `posinf` and negative infinity
+    /// with `neginf`. The infinities default to the largest finite values of `T`.
+    fn nan_to_num(
+        &self,
+        nan: T,
+        posinf: Option<T>,
+        neginf: Option<T>,
+    ) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Whether any element is NaN or infinite. Contiguous matrices are scanned directly
+    /// and nothing is allocated.
+    fn has_non_finite(&self) -> bool;
+}
+
+pub trait AnyAll<T: Num> {
+    /// `1` if any element along `axis` (or of the whole matrix) is non-zero.
+    fn any(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// `1` if every element along `axis` (or of the whole matrix) is non-zero.
+    fn all(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+fn mask<T: Num>(condition: bool) -> T {
+    if condition {
+        T::one()
+    } else {
+        T::zero()
+    }
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> NonFinite<T> for Matrix<M, DimDyn> {
+    fn is_nan(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        self.map(|x| mask(x.is_nan()))
+    }
+
+    fn is_inf(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        self.map(|x| mask(x.is_infinite()))
+    }
+
+    fn is_finite(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        self.map(|x| mask(x.is_finite()))
+    }
+
+    fn nan_to_num(
+        &self,
+        nan: T,
+        posinf: Option<T>,
+        neginf: Option<T>,
+    ) -> Matrix<OwnedMem<T>, DimDyn> {
+        let posinf = posinf.unwrap_or_else(T::max_value);
+        let neginf = neginf.unwrap_or_else(T::min_value);
+        self.map(|x| {
+            if x.is_nan() {
+                nan
+            } else if x == T::infinity() {
+                posinf
+            } else if x == T::neg_infinity() {
+                neginf
+            } else {
+                x
+            }
+        })
+    }
+
+    fn has_non_finite(&self) -> bool {
+        let num_elm = self.shape().num_elm();
+        if num_elm == 0 {
+            return false;
+        }
+        // is_contiguousは0次元やstrideが0の次元があると使えないので、その場合はiteratorで辿る
+        let has_zero_stride = self.stride().slice().contains(&0);
+        if !self.shape().is_empty() && !has_zero_stride && self.shape_stride().is_contiguous() {
+            // 連続したメモリなら順番を気にせず先頭から走査できる
+            let view = self.to_view();
+            let slice = unsafe { std::slice::from_raw_parts(view.as_ptr(), num_elm) };
+            slice.iter().any(|x| !x.is_finite())
+        } else {
+            self.iter().any(|x| !x.is_finite())
+        }
+    }
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> AnyAll<T> for Matrix<M, DimDyn> {
+    fn any(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        reduce_lanes(self, axis, keep_dim, |lane| {
+            mask(lane.iter().any(|&x| x != T::zero()))
+        })
+    }
+
+    fn all(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        reduce_lanes(self, axis, keep_dim, |lane| {
+            mask(lane.iter().all(|&x| x != T::zero()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod finite {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, MatrixSliceDyn, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{asum::Asum, sliding_window::SlidingWindow, transpose::TransposeInplace},
+        slice_dynamic,
+    };
+
+    use super::{AnyAll, NonFinite};
+
+    fn sample() -> OwnedMatrixDyn<f64> {
+        OwnedMatrixDyn::from_vec(
+            vec![1., f64::NAN, f64::INFINITY, -2., f64::NEG_INFINITY, 3.],
+            [2, 3],
+        )
+    }
+
+    #[test]
+    fn masks() {
+        let x = sample();
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 1., 0., 0., 0., 0.], [2, 3]);
+        assert_eq!((x.is_nan().to_view() - ans.to_view()).asum(), 0.);
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 0., 1., 0., 1., 0.], [2, 3]);
+        assert_eq!((x.is_inf().to_view() - ans.to_view()).asum(), 0.);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 0., 0., 1., 0., 1.], [2, 3]);
+        assert_eq!((x.is_finite().to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn any_all() {
+        let x = sample();
+        let nan = x.is_nan();
+        assert_eq!(nan.any(None, false).index_item([]), 1.);
+        assert_eq!(nan.all(None, false).index_item([]), 0.);
+
+        let rows = x.is_finite().any(Some(1), false);
+        assert_eq!(rows.shape().slice(), [2]);
+        let cols = x.is_inf().any(Some(0), true);
+        assert_eq!(cols.shape().slice(), [1, 3]);
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 1., 1.], [1, 3]);
+        assert_eq!((cols.to_view() - ans.to_view()).asum(), 0.);
+
+        let ones = OwnedMatrixDyn::from_vec(vec![1., 2., -1.], [3]);
+        assert_eq!(ones.all(None, false).index_item([]), 1.);
+    }
+
+    #[test]
+    fn nan_to_num() {
+        let x = sample();
+        let y = x.nan_to_num(0., None, Some(-1.));
+        assert_eq!(y.index_item([0, 1]), 0.);
+        assert_eq!(y.index_item([0, 2]), f64::MAX);
+        assert_eq!(y.index_item([1, 1]), -1.);
+        assert_eq!(y.index_item([1, 2]), 3.);
+        assert!(!y.has_non_finite());
+    }
+
+    #[test]
+    fn has_non_finite_views() {
+        let x = sample();
+        assert!(x.has_non_finite());
+        // transposed views are still contiguous
+        assert!(x.transepose_by_index(&[1, 0]).has_non_finite());
+        // the first and last column are finite
+        let finite = x.slice_dyn(slice_dynamic!(.., ..;2));
+        assert!(finite
+            .to_view()
+            .slice_dyn(slice_dynamic!(0, ..))
+            .has_non_finite());
+        assert!(!x.slice_dyn(slice_dynamic!(.., 0)).has_non_finite());
+
+        let clean = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        assert!(!clean.has_non_finite());
+        assert!(!clean.slice_dyn(slice_dynamic!(.., 1)).has_non_finite());
+    }
+
+    #[test]
+    fn has_non_finite_scalar() {
+        let loss = OwnedMatrixDyn::from_vec(vec![f64::NAN], []);
+        assert!(loss.has_non_finite());
+        let loss = OwnedMatrixDyn::from_vec(vec![0.5], []);
+        assert!(!loss.has_non_finite());
+    }
+
+    #[test]
+    fn has_non_finite_zero_stride() {
+        let x = OwnedMatrixDyn::from_vec(vec![f64::INFINITY, 1.], [2]);
+        assert!(x.as_strided([3], [0]).has_non_finite());
+        let y = OwnedMatrixDyn::from_vec(vec![1., f64::INFINITY], [2]);
+        assert!(!y.as_strided([3], [0]).has_non_finite());
+    }
+}
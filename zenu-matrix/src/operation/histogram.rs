@@ -0,0 +1,176 @@
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, OwnedMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::{map_lanes, MatrixElementIter},
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+pub trait Histogram<T: Num> {
+    /// Counts the elements that fall into `bins` equal width bins.
+    ///
+    /// Returns `(counts, edges)`. `edges` has `bins + 1` elements and is shared by every
+    /// lane. `range` defaults to the minimum and maximum of the whole matrix. Bins are
+    /// half-open `[edges[i], edges[i + 1])` except the last one, which also contains the
+    /// upper edge; elements outside of `range` and NaN are not counted.
+    ///
+    /// With `axis == None` every element is counted and `counts` has shape `[bins]`.
+    /// Otherwise each lane along `axis` is counted separately and `axis` is replaced by
+    /// an axis of length `bins`.
+    fn histogram(
+        &self,
+        bins: usize,
+        range: Option<(T, T)>,
+        axis: Option<usize>,
+    ) -> (Matrix<OwnedMem<T>, DimDyn>, Matrix<OwnedMem<T>, DimDyn>);
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Histogram<T> for Matrix<M, DimDyn> {
+    fn histogram(
+        &self,
+        bins: usize,
+        range: Option<(T, T)>,
+        axis: Option<usize>,
+    ) -> (Matrix<OwnedMem<T>, DimDyn>, Matrix<OwnedMem<T>, DimDyn>) {
+        assert!(bins > 0, "histogram needs at least one bin");
+        let (lo, hi) = range.unwrap_or_else(|| self.min_max());
+        assert!(
+            lo <= hi,
+            "histogram range must be ordered, got ({}, {})",
+            lo,
+            hi
+        );
+        // numpyと同じく幅0のrangeは両側に0.5広げる
+        let (lo, hi) = if lo == hi {
+            let half = T::from(0.5).unwrap();
+            (lo - half, hi + half)
+        } else {
+            (lo, hi)
+        };
+
+        let width = (hi - lo) / T::from_usize(bins);
+        let edges = (0..=bins)
+            .map(|i| lo + width * T::from_usize(i))
+            .collect::<Vec<_>>();
+        let edges = Matrix::from_vec(edges, [bins + 1]);
+
+        let count_lane = |lane: &[T], counts: &mut [T]| {
+            for &x in lane {
+                if x.is_nan() || x < lo || x > hi {
+                    continue;
+                }
+                let bin = ((x - lo) / width).floor().to_usize().unwrap().min(bins - 1);
+                counts[bin] += T::one();
+            }
+        };
+
+        let counts = match axis {
+            Some(axis) => map_lanes(self, axis, bins, count_lane),
+            None => {
+                let mut counts = vec![T::zero(); bins];
+                count_lane(&self.iter().collect::<Vec<_>>(), &mut counts);
+                Matrix::from_vec(counts, [bins])
+            }
+        };
+        (counts, edges)
+    }
+}
+
+trait MinMax<T> {
+    fn min_max(&self) -> (T, T);
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> MinMax<T> for Matrix<M, DimDyn> {
+    /// NaNを除いた最小値と最大値
+    fn min_max(&self) -> (T, T) {
+        assert!(
+            self.shape().num_elm() > 0,
+            "histogram of an empty matrix needs an explicit range"
+        );
+        let (lo, hi) = self
+            .iter()
+            .filter(|x| !x.is_nan())
+            .fold((T::infinity(), T::neg_infinity()), |(lo, hi), x| {
+                (lo.min(x), hi.max(x))
+            });
+        // 全てNaNだと(inf, -inf)のままになる
+        assert!(
+            lo <= hi,
+            "histogram of an all-NaN matrix needs an explicit range"
+        );
+        (lo, hi)
+    }
+}
+
+#[cfg(test)]
+mod histogram {
+    use crate::{
+        dim::DimTrait,
+        matrix::{MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::Histogram;
+
+    #[test]
+    fn histogram_all() {
+        let x = OwnedMatrixDyn::from_vec(vec![0., 1., 1., 2., 3., 3., 3., 4.], [8]);
+        let (counts, edges) = x.histogram(4, None, None);
+        let ans_counts = OwnedMatrixDyn::from_vec(vec![1., 2., 1., 4.], [4]);
+        let ans_edges = OwnedMatrixDyn::from_vec(vec![0., 1., 2., 3., 4.], [5]);
+        assert_eq!((counts.to_view() - ans_counts.to_view()).asum(), 0.);
+        assert_eq!((edges.to_view() - ans_edges.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn histogram_range_excludes_outside() {
+        let x = OwnedMatrixDyn::from_vec(vec![-1., 0., 0.5, 1., 2., f64::NAN], [6]);
+        let (counts, _) = x.histogram(2, Some((0., 1.)), None);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        assert_eq!((counts.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn histogram_axis() {
+        let x = OwnedMatrixDyn::from_vec(vec![0., 0., 1., 1., 1., 0.], [2, 3]);
+        let (counts, _) = x.histogram(2, Some((0., 1.)), Some(1));
+        assert_eq!(counts.shape().slice(), [2, 2]);
+        let ans = OwnedMatrixDyn::from_vec(vec![2., 1., 1., 2.], [2, 2]);
+        assert_eq!((counts.to_view() - ans.to_view()).asum(), 0.);
+
+        let t = x.transepose_by_index(&[1, 0]);
+        let (counts, _) = t.histogram(2, Some((0., 1.)), Some(0));
+        assert_eq!(counts.shape().slice(), [2, 2]);
+        assert_eq!((counts.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn histogram_constant() {
+        let x = OwnedMatrixDyn::from_vec(vec![2., 2.], [2]);
+        let (counts, edges) = x.histogram(1, None, None);
+        let ans = OwnedMatrixDyn::from_vec(vec![1.5, 2.5], [2]);
+        assert_eq!((edges.to_view() - ans.to_view()).asum(), 0.);
+        let ans = OwnedMatrixDyn::from_vec(vec![2.], [1]);
+        assert_eq!((counts.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn histogram_nan_skipped_in_range() {
+        let x = OwnedMatrixDyn::from_vec(vec![f64::NAN, 0., 2., f64::NAN], [4]);
+        let (counts, edges) = x.histogram(2, None, None);
+        let ans = OwnedMatrixDyn::from_vec(vec![0., 1., 2.], [3]);
+        assert_eq!((edges.to_view() - ans.to_view()).asum(), 0.);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 1.], [2]);
+        assert_eq!((counts.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "histogram of an all-NaN matrix needs an explicit range")]
+    fn histogram_all_nan_needs_range() {
+        let x = OwnedMatrixDyn::from_vec(vec![f64::NAN, f64::NAN], [2]);
+        x.histogram(2, None, None);
+    }
+}
@@ -0,0 +1,499 @@
+//! Image tensor utilities.
+//!
+//! Every operation takes the [`ImageLayout`] of its input. Inputs are either a single
+//! image (`CHW` / `HWC`, 3-D) or a batch (`NCHW` / `NHWC`, 4-D), and the result keeps the
+//! layout and the batch axis of the input.
+//!
+//! ```
+//! use zenu_matrix::{
+//!     dim::DimTrait,
+//!     matrix::{MatrixBase, OwnedMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     operation::image::{ImageLayout, ImageOps, Interpolation},
+//! };
+//!
+//! // a batch of two 4x4 RGB images
+//! let x = OwnedMatrixDyn::from_vec(vec![0.5; 2 * 4 * 4 * 3], [2, 4, 4, 3]);
+//! let x = x.hwc_to_chw();
+//! assert_eq!(x.shape().slice(), [2, 3, 4, 4]);
+//!
+//! let x = x.resize(ImageLayout::Nchw, 8, 8, Interpolation::Bilinear);
+//! let x = x.center_crop(ImageLayout::Nchw, 6, 6);
+//! let x = x.normalize_channels(ImageLayout::Nchw, &[0.5, 0.5, 0.5], &[0.25, 0.25, 0.25]);
+//! assert_eq!(x.shape().slice(), [2, 3, 6, 6]);
+//! ```
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, MatrixSliceDyn, OwnedMatrix, ToViewMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::MatrixElementIter,
+    memory::ToViewMemory,
+    memory_impl::{OwnedMem, ViewMem},
+    num::Num,
+    shape_stride::ShapeStride,
+    slice::{dynamic::Slice, slice_dim::SliceDim},
+};
+
+use super::{
+    reshape::ReshapeNoAlloc, to_default_stride::ToDefaultStride, transpose::TransposeInplace,
+};
+
+/// Memory layout of an image matrix. The batch axis `N` is optional.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageLayout {
+    /// `[N, C, H, W]` or `[C, H, W]`
+    Nchw,
+    /// `[N, H, W, C]` or `[H, W, C]`
+    Nhwc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    /// Linear interpolation between the 2x2 nearest pixels.
+    Bilinear,
+    /// Cubic convolution over the 4x4 nearest pixels (`a = -0.75`, as in PyTorch).
+    Bicubic,
+}
+
+pub trait ImageOps<T: Num> {
+    /// Resizes the spatial axes to `height` x `width`.
+    ///
+    /// Pixel centers are aligned like PyTorch's `align_corners=False`.
+    fn resize(
+        &self,
+        layout: ImageLayout,
+        height: usize,
+        width: usize,
+        method: Interpolation,
+    ) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Zero-copy view of the `height` x `width` region whose top left pixel is at
+    /// (`top`, `left`).
+    fn crop(
+        &self,
+        layout: ImageLayout,
+        top: usize,
+        left: usize,
+        height: usize,
+        width: usize,
+    ) -> Matrix<ViewMem<T>, DimDyn>;
+    /// Zero-copy view of the centered `height` x `width` region.
+    fn center_crop(
+        &self,
+        layout: ImageLayout,
+        height: usize,
+        width: usize,
+    ) -> Matrix<ViewMem<T>, DimDyn>;
+    /// `HWC` -> `CHW` (or `NHWC` -> `NCHW`).
+    fn hwc_to_chw(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// `CHW` -> `HWC` (or `NCHW` -> `NHWC`).
+    fn chw_to_hwc(&self) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// 3 channel RGB to 1 channel luma with the ITU-R BT.601 weights.
+    fn rgb_to_grayscale(&self, layout: ImageLayout) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Repeats the single channel of a grayscale image into 3 channels.
+    fn grayscale_to_rgb(&self, layout: ImageLayout) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// `(x - mean[c]) / std[c]` for every channel `c`.
+    fn normalize_channels(
+        &self,
+        layout: ImageLayout,
+        mean: &[T],
+        std: &[T],
+    ) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> ImageOps<T> for Matrix<M, DimDyn> {
+    fn resize(
+        &self,
+        layout: ImageLayout,
+        height: usize,
+        width: usize,
+        method: Interpolation,
+    ) -> Matrix<OwnedMem<T>, DimDyn> {
+        let (image, [n, c, in_h, in_w]) = NchwImage::new(self, layout);
+        assert!(
+            in_h > 0 && in_w > 0,
+            "cannot resize an image with no pixels"
+        );
+        let rows = taps::<T>(in_h, height, method);
+        let cols = taps::<T>(in_w, width, method);
+
+        let mut out = Vec::with_capacity(n * c * height * width);
+        for plane in image.data.chunks(in_h * in_w) {
+            for (row_idx, row_weight) in &rows {
+                for (col_idx, col_weight) in &cols {
+                    let mut v = T::zero();
+                    for (&y, &wy) in row_idx.iter().zip(row_weight) {
+                        for (&x, &wx) in col_idx.iter().zip(col_weight) {
+                            v += plane[y * in_w + x] * wy * wx;
+                        }
+                    }
+                    out.push(v);
+                }
+            }
+        }
+        image.finish(out, c, height, width)
+    }
+
+    fn crop(
+        &self,
+        layout: ImageLayout,
+        top: usize,
+        left: usize,
+        height: usize,
+        width: usize,
+    ) -> Matrix<ViewMem<T>, DimDyn> {
+        let (h_axis, w_axis) = spatial_axes(self.shape().len(), layout);
+        let shape = self.shape();
+        assert!(
+            height > 0 && width > 0,
+            "crop size must be greater than zero"
+        );
+        assert!(
+            top + height <= shape[h_axis] && left + width <= shape[w_axis],
+            "crop {}x{} at ({}, {}) is outside of the {}x{} image",
+            height,
+            width,
+            top,
+            left,
+            shape[h_axis],
+            shape[w_axis]
+        );
+        let mut index = vec![SliceDim::from(..); shape.len()];
+        index[h_axis] = SliceDim::from(top..top + height);
+        index[w_axis] = SliceDim::from(left..left + width);
+        self.slice_dyn(Slice::from(index.as_slice()))
+    }
+
+    fn center_crop(
+        &self,
+        layout: ImageLayout,
+        height: usize,
+        width: usize,
+    ) -> Matrix<ViewMem<T>, DimDyn> {
+        let (h_axis, w_axis) = spatial_axes(self.shape().len(), layout);
+        let shape = self.shape();
+        assert!(
+            height <= shape[h_axis] && width <= shape[w_axis],
+            "center crop {}x{} is larger than the {}x{} image",
+            height,
+            width,
+            shape[h_axis],
+            shape[w_axis]
+        );
+        let top = (shape[h_axis] - height) / 2;
+        let left = (shape[w_axis] - width) / 2;
+        self.crop(layout, top, left, height, width)
+    }
+
+    fn hwc_to_chw(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        let (image, [_, c, h, w]) = NchwImage::new(self, ImageLayout::Nhwc);
+        let image = NchwImage {
+            layout: ImageLayout::Nchw,
+            ..image
+        };
+        let data = image.data.clone();
+        image.finish(data, c, h, w)
+    }
+
+    fn chw_to_hwc(&self) -> Matrix<OwnedMem<T>, DimDyn> {
+        let (image, [_, c, h, w]) = NchwImage::new(self, ImageLayout::Nchw);
+        let image = NchwImage {
+            layout: ImageLayout::Nhwc,
+            ..image
+        };
+        let data = image.data.clone();
+        image.finish(data, c, h, w)
+    }
+
+    fn rgb_to_grayscale(&self, layout: ImageLayout) -> Matrix<OwnedMem<T>, DimDyn> {
+        let (image, [_, c, h, w]) = NchwImage::new(self, layout);
+        assert_eq!(c, 3, "rgb_to_grayscale needs 3 channels, got {}", c);
+        let weights = [0.299, 0.587, 0.114].map(|x| T::from(x).unwrap());
+        let plane = h * w;
+        let mut out = Vec::with_capacity(image.data.len() / 3);
+        for rgb in image.data.chunks(3 * plane) {
+            for i in 0..plane {
+                out.push(
+                    rgb[i] * weights[0]
+                        + rgb[plane + i] * weights[1]
+                        + rgb[2 * plane + i] * weights[2],
+                );
+            }
+        }
+        image.finish(out, 1, h, w)
+    }
+
+    fn grayscale_to_rgb(&self, layout: ImageLayout) -> Matrix<OwnedMem<T>, DimDyn> {
+        let (image, [_, c, h, w]) = NchwImage::new(self, layout);
+        assert_eq!(c, 1, "grayscale_to_rgb needs 1 channel, got {}", c);
+        let mut out = Vec::with_capacity(image.data.len() * 3);
+        for gray in image.data.chunks(h * w) {
+            for _ in 0..3 {
+                out.extend_from_slice(gray);
+            }
+        }
+        image.finish(out, 3, h, w)
+    }
+
+    fn normalize_channels(
+        &self,
+        layout: ImageLayout,
+        mean: &[T],
+        std: &[T],
+    ) -> Matrix<OwnedMem<T>, DimDyn> {
+        let (image, [_, c, h, w]) = NchwImage::new(self, layout);
+        assert!(
+            mean.len() == c && std.len() == c,
+            "mean and std need one value per channel ({}), got {} and {}",
+            c,
+            mean.len(),
+            std.len()
+        );
+        let mut out = image.data.clone();
+        for (i, plane) in out.chunks_mut(h * w).enumerate() {
+            let (mean, std) = (mean[i % c], std[i % c]);
+            for x in plane {
+                *x = (*x - mean) / std;
+            }
+        }
+        image.finish(out, c, h, w)
+    }
+}
+
+/// (H軸, W軸)のindex
+fn spatial_axes(ndim: usize, layout: ImageLayout) -> (usize, usize) {
+    assert!(
+        ndim == 3 || ndim == 4,
+        "image matrices must be 3-D or 4-D, got {}-D",
+        ndim
+    );
+    match layout {
+        ImageLayout::Nchw => (ndim - 2, ndim - 1),
+        ImageLayout::Nhwc => (ndim - 3, ndim - 2),
+    }
+}
+
+/// NCHWのdefault strideに並べ直した画像と、元のlayoutに戻すための情報
+struct NchwImage<T> {
+    data: Vec<T>,
+    batch: usize,
+    batched: bool,
+    layout: ImageLayout,
+}
+
+impl<T: Num> NchwImage<T> {
+    fn new<M: ToViewMemory<Item = T>>(
+        x: &Matrix<M, DimDyn>,
+        layout: ImageLayout,
+    ) -> (Self, [usize; 4]) {
+        let ndim = x.shape().len();
+        spatial_axes(ndim, layout);
+        let batched = ndim == 4;
+
+        // バッチ軸がなければstride 0の長さ1の軸を足す
+        let mut shape = DimDyn::default();
+        let mut stride = DimDyn::default();
+        if !batched {
+            shape.push_dim(1);
+            stride.push_dim(0);
+        }
+        for (&sh, &st) in x.shape().slice().iter().zip(x.stride().slice()) {
+            shape.push_dim(sh);
+            stride.push_dim(st);
+        }
+        let mut view = x.to_view();
+        view.update_shape_stride(ShapeStride::new(shape, stride));
+        let view = match layout {
+            ImageLayout::Nchw => view,
+            ImageLayout::Nhwc => view.transepose_by_index(&[0, 3, 1, 2]),
+        };
+        let dims = [
+            view.shape()[0],
+            view.shape()[1],
+            view.shape()[2],
+            view.shape()[3],
+        ];
+        let image = Self {
+            data: view.iter().collect(),
+            batch: dims[0],
+            batched,
+            layout,
+        };
+        (image, dims)
+    }
+
+    /// NCHWの`data`を入力と同じlayoutとバッチ軸の有無のMatrixにする
+    fn finish(self, data: Vec<T>, c: usize, h: usize, w: usize) -> Matrix<OwnedMem<T>, DimDyn> {
+        let nchw = Matrix::<OwnedMem<T>, DimDyn>::from_vec(data, [self.batch, c, h, w]);
+        let out = match self.layout {
+            ImageLayout::Nchw => nchw,
+            ImageLayout::Nhwc => nchw.transepose_by_index(&[0, 2, 3, 1]).to_default_stride(),
+        };
+        if self.batched {
+            out
+        } else {
+            let shape = DimDyn::from(&out.shape().slice()[1..]);
+            out.reshape_no_alloc_owned(shape)
+        }
+    }
+}
+
+/// 出力の各座標について、参照する入力のindexと重みを返す
+fn taps<T: Num>(in_len: usize, out_len: usize, method: Interpolation) -> Vec<(Vec<usize>, Vec<T>)> {
+    let scale = in_len as f64 / out_len as f64;
+    let clamp = |i: isize| i.clamp(0, in_len as isize - 1) as usize;
+    (0..out_len)
+        .map(|dst| {
+            let (idx, weight): (Vec<usize>, Vec<f64>) = match method {
+                Interpolation::Nearest => {
+                    let src = ((dst as f64 * scale).floor() as usize).min(in_len - 1);
+                    (vec![src], vec![1.])
+                }
+                Interpolation::Bilinear => {
+                    let src = ((dst as f64 + 0.5) * scale - 0.5).max(0.);
+                    let i0 = src.floor();
+                    let t = src - i0;
+                    let i0 = i0 as isize;
+                    (vec![clamp(i0), clamp(i0 + 1)], vec![1. - t, t])
+                }
+                Interpolation::Bicubic => {
+                    let src = (dst as f64 + 0.5) * scale - 0.5;
+                    let i0 = src.floor();
+                    let t = src - i0;
+                    let i0 = i0 as isize;
+                    let idx = (-1..=2).map(|k| clamp(i0 + k)).collect();
+                    (idx, cubic_weights(t).to_vec())
+                }
+            };
+            let weight = weight.into_iter().map(|w| T::from(w).unwrap()).collect();
+            (idx, weight)
+        })
+        .collect()
+}
+
+/// Keysのcubic convolutionの重み
+fn cubic_weights(t: f64) -> [f64; 4] {
+    const A: f64 = -0.75;
+    let near = |x: f64| ((A + 2.) * x - (A + 3.)) * x * x + 1.;
+    let far = |x: f64| ((A * x - 5. * A) * x + 8. * A) * x - 4. * A;
+    [far(t + 1.), near(t), near(1. - t), far(2. - t)]
+}
+
+#[cfg(test)]
+mod image {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::asum::Asum,
+    };
+
+    use super::{ImageLayout, ImageOps, Interpolation};
+
+    fn arange(shape: &[usize]) -> OwnedMatrixDyn<f64> {
+        let n = shape.iter().product::<usize>();
+        OwnedMatrixDyn::from_vec((0..n).map(|x| x as f64).collect(), shape)
+    }
+
+    #[test]
+    fn layout_roundtrip() {
+        let x = arange(&[2, 3, 4]);
+        let chw = x.hwc_to_chw();
+        assert_eq!(chw.shape().slice(), [4, 2, 3]);
+        assert_eq!(chw.index_item([1, 0, 2]), x.index_item([0, 2, 1]));
+        let hwc = chw.chw_to_hwc();
+        assert_eq!((hwc.to_view() - x.to_view()).asum(), 0.);
+
+        let x = arange(&[2, 3, 4, 5]);
+        let nchw = x.hwc_to_chw();
+        assert_eq!(nchw.shape().slice(), [2, 5, 3, 4]);
+        assert_eq!(nchw.index_item([1, 4, 2, 3]), x.index_item([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn resize_nearest() {
+        let x = arange(&[1, 2, 2]);
+        let y = x.resize(ImageLayout::Nchw, 4, 4, Interpolation::Nearest);
+        let ans = OwnedMatrixDyn::from_vec(
+            vec![
+                0., 0., 1., 1., 0., 0., 1., 1., 2., 2., 3., 3., 2., 2., 3., 3.,
+            ],
+            [1, 4, 4],
+        );
+        assert_eq!((y.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn resize_bilinear() {
+        // torch.nn.functional.interpolate(x, size=4, mode="bilinear", align_corners=False)
+        let x = arange(&[1, 1, 2, 2]);
+        let y = x.resize(ImageLayout::Nchw, 4, 4, Interpolation::Bilinear);
+        let ans = OwnedMatrixDyn::from_vec(
+            vec![
+                0., 0.25, 0.75, 1., 0.5, 0.75, 1.25, 1.5, 1.5, 1.75, 2.25, 2.5, 2., 2.25, 2.75, 3.,
+            ],
+            [1, 1, 4, 4],
+        );
+        assert!((y.to_view() - ans.to_view()).asum() < 1e-12);
+    }
+
+    #[test]
+    fn resize_bicubic() {
+        // torch.nn.functional.interpolate(x, size=(1, 4), mode="bicubic", align_corners=False)
+        let x = OwnedMatrixDyn::from_vec(vec![0., 1.], [1, 1, 2]);
+        let y = x.resize(ImageLayout::Nchw, 1, 4, Interpolation::Bicubic);
+        let ans = OwnedMatrixDyn::from_vec(
+            vec![-0.10546875, 0.2265625, 0.7734375, 1.10546875],
+            [1, 1, 4],
+        );
+        assert!((y.to_view() - ans.to_view()).asum() < 1e-12);
+
+        // downscaling a constant image keeps it constant
+        let x = OwnedMatrixDyn::from_vec(vec![2.; 2 * 6 * 6], [6, 6, 2]);
+        let y = x.resize(ImageLayout::Nhwc, 3, 3, Interpolation::Bicubic);
+        assert_eq!(y.shape().slice(), [3, 3, 2]);
+        assert!((y - 2.).asum() < 1e-12);
+    }
+
+    #[test]
+    fn crop_views() {
+        let x = arange(&[1, 4, 5, 2]);
+        let c = x.crop(ImageLayout::Nhwc, 1, 2, 2, 3);
+        assert_eq!(c.shape().slice(), [1, 2, 3, 2]);
+        assert_eq!(c.index_item([0, 0, 0, 1]), x.index_item([0, 1, 2, 1]));
+
+        let x = arange(&[3, 5, 6]);
+        let c = x.center_crop(ImageLayout::Nchw, 3, 2);
+        assert_eq!(c.shape().slice(), [3, 3, 2]);
+        assert_eq!(c.index_item([2, 0, 0]), x.index_item([2, 1, 2]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn crop_out_of_bounds() {
+        let x = arange(&[1, 4, 4]);
+        x.crop(ImageLayout::Nchw, 2, 0, 3, 4);
+    }
+
+    #[test]
+    fn grayscale() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 0., 0., 0., 1., 0.], [1, 2, 3]);
+        let g = x.rgb_to_grayscale(ImageLayout::Nhwc);
+        assert_eq!(g.shape().slice(), [1, 2, 1]);
+        assert!((g.index_item([0, 0, 0]) - 0.299f64).abs() < 1e-12);
+        assert!((g.index_item([0, 1, 0]) - 0.587f64).abs() < 1e-12);
+
+        let rgb = g.grayscale_to_rgb(ImageLayout::Nhwc);
+        assert_eq!(rgb.shape().slice(), [1, 2, 3]);
+        assert_eq!(rgb.index_item([0, 1, 2]), g.index_item([0, 1, 0]));
+    }
+
+    #[test]
+    fn normalize_channels() {
+        let x = arange(&[2, 2, 1, 2]);
+        let y = x.normalize_channels(ImageLayout::Nchw, &[1., 2.], &[2., 4.]);
+        let ans =
+            OwnedMatrixDyn::from_vec(vec![-0.5, 0., 0., 0.25, 1.5, 2., 1., 1.25], [2, 2, 1, 2]);
+        assert!((y.to_view() - ans.to_view()).asum() < 1e-12);
+    }
+}
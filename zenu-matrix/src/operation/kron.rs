@@ -0,0 +1,124 @@
+use crate::{
+    dim::{default_stride, DimDyn, DimTrait},
+    matrix::{MatrixBase, OwnedMatrix, ToViewMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::MatrixElementIter,
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+pub trait Kron<T: Num> {
+    /// Kronecker product.
+    ///
+    /// The block of the result at index `i` of `self` is `self[i] * other`, so each axis
+    /// of the result has length `self.shape()[k] * other.shape()[k]`. When the number of
+    /// dimensions differs, the smaller one is padded with leading axes of length 1.
+    fn kron<N, E>(&self, other: &Matrix<N, E>) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        N: ToViewMemory<Item = T>,
+        E: DimTrait;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>, D: DimTrait> Kron<T> for Matrix<M, D> {
+    fn kron<N, E>(&self, other: &Matrix<N, E>) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        N: ToViewMemory<Item = T>,
+        E: DimTrait,
+    {
+        let a = self.to_view().into_dyn_dim();
+        let b = other.to_view().into_dyn_dim();
+        let ndim = a.shape().len().max(b.shape().len());
+        let a_shape = pad_leading(a.shape(), ndim);
+        let b_shape = pad_leading(b.shape(), ndim);
+
+        let mut out_shape = DimDyn::default();
+        for k in 0..ndim {
+            out_shape.push_dim(a_shape[k] * b_shape[k]);
+        }
+        let out_stride = default_stride(out_shape);
+
+        // aのindex iはoutのi * b_shapeに、bのindex jはoutの+ jに対応する
+        let mut a_scale = DimDyn::default();
+        for k in 0..ndim {
+            a_scale.push_dim(b_shape[k] * out_stride[k]);
+        }
+        let a_offsets = row_major_offsets(a_shape, a_scale);
+        let b_offsets = row_major_offsets(b_shape, out_stride);
+
+        let mut out = vec![T::zero(); out_shape.num_elm()];
+        let b_values = b.iter().collect::<Vec<_>>();
+        for (a_value, a_offset) in a.iter().zip(a_offsets) {
+            for (&b_value, &b_offset) in b_values.iter().zip(&b_offsets) {
+                out[a_offset + b_offset] = a_value * b_value;
+            }
+        }
+        Matrix::from_vec(out, out_shape)
+    }
+}
+
+fn pad_leading(shape: DimDyn, ndim: usize) -> DimDyn {
+    let mut padded = DimDyn::default();
+    for _ in shape.len()..ndim {
+        padded.push_dim(1);
+    }
+    for &len in shape.slice() {
+        padded.push_dim(len);
+    }
+    padded
+}
+
+/// `shape`の全indexをrow-major順に辿り、各indexと`scale`の内積を返す
+fn row_major_offsets(shape: DimDyn, scale: DimDyn) -> Vec<usize> {
+    let mut offsets = vec![0];
+    for k in 0..shape.len() {
+        offsets = offsets
+            .iter()
+            .flat_map(|&offset| (0..shape[k]).map(move |i| offset + i * scale[k]))
+            .collect();
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod kron {
+    use crate::{
+        dim::DimTrait,
+        matrix::{MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::{OwnedMatrix2D, OwnedMatrixDyn},
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::Kron;
+
+    #[test]
+    fn kron_2d() {
+        let a = OwnedMatrix2D::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        let b = OwnedMatrixDyn::from_vec(vec![0., 5., 6., 7.], [2, 2]);
+        let k = a.kron(&b);
+        assert_eq!(k.shape().slice(), [4, 4]);
+        #[rustfmt::skip]
+        let ans = OwnedMatrixDyn::from_vec(
+            vec![
+                0., 5., 0., 10.,
+                6., 7., 12., 14.,
+                0., 15., 0., 20.,
+                18., 21., 24., 28.,
+            ],
+            [4, 4],
+        );
+        assert_eq!((k.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn kron_broadcast_ndim_and_view() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        let b = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        // [[1, 3], [2, 4]]
+        let bt = b.transepose_by_index(&[1, 0]);
+        let k = a.kron(&bt);
+        assert_eq!(k.shape().slice(), [2, 4]);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 3., 2., 6., 2., 4., 4., 8.], [2, 4]);
+        assert_eq!((k.to_view() - ans.to_view()).asum(), 0.);
+    }
+}
@@ -0,0 +1,93 @@
+//! Numerically stable `log(sum(exp(x)))`.
+//!
+//! The maximum of each lane is subtracted before exponentiating, so large inputs do
+//! not overflow and very negative inputs do not underflow to `log(0) = -inf`.
+//!
+//! ```
+//! use zenu_matrix::{
+//!     matrix::{IndexItem, OwnedMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     operation::logsumexp::LogSumExp,
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec(vec![1000., 1000.], [1, 2]);
+//! let lse = x.logsumexp(1, false);
+//! assert!((lse.index_item([0]) - (1000. + 2f64.ln())).abs() < 1e-9);
+//! ```
+use crate::{
+    dim::DimDyn, matrix_impl::Matrix, matrix_iter::reduce_lanes, memory::ToViewMemory,
+    memory_impl::OwnedMem, num::Num,
+};
+
+pub trait LogSumExp<T: Num> {
+    fn logsumexp(&self, axis: usize, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> LogSumExp<T> for Matrix<M, DimDyn> {
+    fn logsumexp(&self, axis: usize, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        reduce_lanes(self, Some(axis), keep_dim, logsumexp_kernel_cpu)
+    }
+}
+
+pub(crate) fn logsumexp_kernel_cpu<T: Num>(lane: &[T]) -> T {
+    let max = lane
+        .iter()
+        .fold(T::neg_infinity(), |max, &x| if x > max { x } else { max });
+    // 全て-infならそのまま-inf、infを含むならinfを返す
+    if max.is_infinite() {
+        return max;
+    }
+    let sum = lane.iter().fold(T::zero(), |sum, &x| sum + (x - max).exp());
+    max + sum.ln()
+}
+
+#[cfg(test)]
+mod logsumexp {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{asum::Asum, transpose::TransposeInplace},
+    };
+
+    use super::LogSumExp;
+
+    #[test]
+    fn logsumexp_2d() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let ans = x.logsumexp(1, false);
+        assert_eq!(ans.shape().slice(), [2]);
+        let expected = OwnedMatrixDyn::from_vec(vec![3.40760596, 6.40760596], [2]);
+        assert!((ans.to_view() - expected.to_view()).asum() < 1e-6);
+
+        let ans = x.logsumexp(0, true);
+        assert_eq!(ans.shape().slice(), [1, 3]);
+        let expected = OwnedMatrixDyn::from_vec(vec![4.04858735, 5.04858735, 6.04858735], [1, 3]);
+        assert!((ans.to_view() - expected.to_view()).asum() < 1e-6);
+    }
+
+    #[test]
+    fn logsumexp_large_and_small() {
+        let x = OwnedMatrixDyn::from_vec(vec![1000., 1000., -1000., -1000.], [2, 2]);
+        let ans = x.logsumexp(1, false);
+        assert!((ans.index_item([0]) - (1000. + 2f64.ln())).abs() < 1e-9);
+        assert!((ans.index_item([1]) - (-1000. + 2f64.ln())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn logsumexp_neg_inf() {
+        let x = OwnedMatrixDyn::from_vec(vec![f64::NEG_INFINITY, f64::NEG_INFINITY, 0.], [3]);
+        assert_eq!(x.logsumexp(0, false).index_item([]), 0.);
+        let x = OwnedMatrixDyn::from_vec(vec![f64::NEG_INFINITY; 2], [2]);
+        assert_eq!(x.logsumexp(0, false).index_item([]), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn logsumexp_view() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let t = x.transepose_by_index(&[1, 0]);
+        let ans = t.logsumexp(0, false);
+        let expected = OwnedMatrixDyn::from_vec(vec![3.40760596, 6.40760596], [2]);
+        assert!((ans.to_view() - expected.to_view()).asum() < 1e-6);
+    }
+}
@@ -4,15 +4,29 @@ pub mod basic_operations;
 pub mod broadcast;
 pub mod clip;
 pub mod copy_from;
+pub mod cross;
+pub mod cumulative;
+pub mod diag;
 pub mod dot;
 pub mod exp;
+pub mod fft;
+pub mod finite;
+pub mod histogram;
+pub mod image;
+pub mod kron;
 pub mod log;
+pub mod logsumexp;
 pub mod max;
 pub mod mean;
 pub mod mul;
+pub mod norm;
 pub mod norm2;
+pub mod outer;
+pub mod prod;
+pub mod quantile;
 pub mod relu;
 pub mod reshape;
+pub mod sliding_window;
 pub mod softmax;
 pub mod sum;
 pub mod to_default_stride;
@@ -0,0 +1,218 @@
+//! Vector and matrix norms along an axis.
+//!
+//! Works on any matrix or view, including non-contiguous ones.
+//!
+//! ```
+//! use zenu_matrix::{
+//!     matrix::{IndexItem, OwnedMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     operation::norm::{Norm, NormOrd},
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec(vec![3., -4., 0., 1.], [2, 2]);
+//! let rows = x.norm(NormOrd::L2, Some(1), false);
+//! assert_eq!(rows.index_item([0]), 5.);
+//! let max = x.norm(NormOrd::Inf, None, false);
+//! assert_eq!(max.index_item([]), 4.);
+//!
+//! let unit = x.normalize(1);
+//! assert_eq!(unit.index_item([0, 0]), 0.6);
+//! ```
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::MatrixBase,
+    matrix_impl::Matrix,
+    matrix_iter::{map_lanes, reduce_lanes},
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormOrd {
+    /// Sum of absolute values.
+    L1,
+    /// Euclidean norm.
+    L2,
+    /// Maximum absolute value.
+    Inf,
+    /// Square root of the sum of squares of every element. Only valid with `axis == None`.
+    Frobenius,
+}
+
+pub trait Norm<T: Num> {
+    /// `ord` norm of each lane along `axis`, or of every element when `axis` is `None`.
+    fn norm(
+        &self,
+        ord: NormOrd,
+        axis: Option<usize>,
+        keep_dim: bool,
+    ) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Divides each lane along `axis` by its L2 norm.
+    ///
+    /// Lanes whose norm is smaller than `1e-12` are divided by `1e-12` instead, so zero
+    /// lanes stay zero.
+    fn normalize(&self, axis: usize) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Norm<T> for Matrix<M, DimDyn> {
+    fn norm(
+        &self,
+        ord: NormOrd,
+        axis: Option<usize>,
+        keep_dim: bool,
+    ) -> Matrix<OwnedMem<T>, DimDyn> {
+        let kernel = match ord {
+            NormOrd::L1 => l1_kernel_cpu,
+            NormOrd::L2 => l2_kernel_cpu,
+            NormOrd::Inf => inf_kernel_cpu,
+            NormOrd::Frobenius => {
+                assert!(
+                    axis.is_none(),
+                    "the Frobenius norm is taken over the whole matrix, use NormOrd::L2 along an axis"
+                );
+                l2_kernel_cpu
+            }
+        };
+        reduce_lanes(self, axis, keep_dim, kernel)
+    }
+
+    fn normalize(&self, axis: usize) -> Matrix<OwnedMem<T>, DimDyn> {
+        let shape = self.shape();
+        assert!(
+            axis < shape.len(),
+            "axis {} is out of range for a {}-D matrix",
+            axis,
+            shape.len()
+        );
+        let len = shape[axis];
+        let eps = T::from(1e-12).unwrap();
+        map_lanes(self, axis, len, |lane, out| {
+            let norm = l2_kernel_cpu(lane);
+            let norm = if norm < eps { eps } else { norm };
+            for (o, &x) in out.iter_mut().zip(lane) {
+                *o = x / norm;
+            }
+        })
+    }
+}
+
+fn l1_kernel_cpu<T: Num>(lane: &[T]) -> T {
+    lane.iter().fold(T::zero(), |acc, &x| acc + x.abs())
+}
+
+fn inf_kernel_cpu<T: Num>(lane: &[T]) -> T {
+    // maxはNaNを無視するので、NaNがあればそのまま返す
+    lane.iter().fold(T::zero(), |acc, &x| {
+        if acc.is_nan() || x.is_nan() {
+            T::nan()
+        } else {
+            acc.max(x.abs())
+        }
+    })
+}
+
+/// 最大値でscaleしてから二乗和を取り、overflowとunderflowを防ぐ
+fn l2_kernel_cpu<T: Num>(lane: &[T]) -> T {
+    let scale = inf_kernel_cpu(lane);
+    if scale == T::zero() || scale.is_infinite() {
+        return scale;
+    }
+    let sum = lane.iter().fold(T::zero(), |acc, &x| {
+        let x = x / scale;
+        acc + x * x
+    });
+    scale * sum.sqrt()
+}
+
+#[cfg(test)]
+mod norm {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, MatrixSliceDyn, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::asum::Asum,
+        slice_dynamic,
+    };
+
+    use super::{Norm, NormOrd};
+
+    #[test]
+    fn norm_all() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., -2., 2., -4.], [2, 2]);
+        assert_eq!(x.norm(NormOrd::L1, None, false).index_item([]), 9.);
+        assert_eq!(x.norm(NormOrd::L2, None, false).index_item([]), 5.);
+        assert_eq!(x.norm(NormOrd::Frobenius, None, false).index_item([]), 5.);
+        assert_eq!(x.norm(NormOrd::Inf, None, false).index_item([]), 4.);
+        let ans = x.norm(NormOrd::Frobenius, None, true);
+        assert_eq!(ans.shape().slice(), [1, 1]);
+    }
+
+    #[test]
+    fn norm_axis() {
+        let x = OwnedMatrixDyn::from_vec(vec![3., 0., -4., 1.], [2, 2]);
+        let ans = x.norm(NormOrd::L2, Some(0), false);
+        let expected = OwnedMatrixDyn::from_vec(vec![5., 1.], [2]);
+        assert_eq!((ans.to_view() - expected.to_view()).asum(), 0.);
+
+        let ans = x.norm(NormOrd::L1, Some(1), true);
+        assert_eq!(ans.shape().slice(), [2, 1]);
+        assert_eq!(ans.index_item([1, 0]), 5.);
+
+        let ans = x.norm(NormOrd::Inf, Some(0), false);
+        assert_eq!(ans.index_item([0]), 4.);
+    }
+
+    #[test]
+    fn norm_strided_view() {
+        let x = OwnedMatrixDyn::from_vec((0..12).map(|x| x as f64).collect(), [3, 4]);
+        // [[1, 3], [5, 7], [9, 11]]
+        let s = x.slice_dyn(slice_dynamic!(.., 1..;2));
+        let ans = s.norm(NormOrd::L1, Some(0), false);
+        assert_eq!(ans.index_item([0]), 15.);
+        assert_eq!(ans.index_item([1]), 21.);
+    }
+
+    #[test]
+    fn norm_no_overflow() {
+        let x = OwnedMatrixDyn::from_vec(vec![3e200, 4e200], [2]);
+        let ans: f64 = x.norm(NormOrd::L2, None, false).index_item([]);
+        assert!((ans / 5e200 - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn norm_propagates_nan() {
+        let x = OwnedMatrixDyn::from_vec(vec![f64::NAN, 1., f64::INFINITY, 0.], [2, 2]);
+        let inf = x.norm(NormOrd::Inf, Some(1), false);
+        assert!(inf.index_item([0]).is_nan());
+        assert_eq!(inf.index_item([1]), f64::INFINITY);
+        assert!(x.norm(NormOrd::Inf, None, false).index_item([]).is_nan());
+        assert!(x.norm(NormOrd::L2, None, false).index_item([]).is_nan());
+        assert!(x.norm(NormOrd::L1, None, false).index_item([]).is_nan());
+
+        let y = OwnedMatrixDyn::from_vec(vec![0., f64::NAN], [2]);
+        assert!(y.norm(NormOrd::L2, None, false).index_item([]).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "axis 2 is out of range for a 2-D matrix")]
+    fn normalize_axis_out_of_range() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        x.normalize(2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn frobenius_with_axis() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        x.norm(NormOrd::Frobenius, Some(0), false);
+    }
+
+    #[test]
+    fn normalize() {
+        let x = OwnedMatrixDyn::from_vec(vec![3., 0., 4., 0.], [2, 2]);
+        let ans = x.normalize(0);
+        let expected = OwnedMatrixDyn::from_vec(vec![0.6, 0., 0.8, 0.], [2, 2]);
+        assert!((ans.to_view() - expected.to_view()).asum() < 1e-12);
+    }
+}
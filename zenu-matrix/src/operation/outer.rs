@@ -0,0 +1,90 @@
+use crate::{
+    blas::{Blas, BlasLayout},
+    constructor::zeros::Zeros,
+    dim::{DimDyn, DimTrait},
+    matrix::{AsMutPtr, AsPtr, MatrixBase, ToViewMatrix, ToViewMutMatrix},
+    matrix_impl::Matrix,
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+    num::Num,
+};
+
+pub trait Outer<T: Num> {
+    /// Outer product `out[i, j] = self[i] * other[j]` of two 1-D matrices.
+    fn outer<N, E>(&self, other: &Matrix<N, E>) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        N: ToViewMemory<Item = T>,
+        E: DimTrait;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>, D: DimTrait> Outer<T> for Matrix<M, D> {
+    fn outer<N, E>(&self, other: &Matrix<N, E>) -> Matrix<OwnedMem<T>, DimDyn>
+    where
+        N: ToViewMemory<Item = T>,
+        E: DimTrait,
+    {
+        let x = self.to_view().into_dyn_dim();
+        let y = other.to_view().into_dyn_dim();
+        assert!(
+            x.shape().len() == 1 && y.shape().len() == 1,
+            "outer needs two 1-D matrices, got {}-D and {}-D",
+            x.shape().len(),
+            y.shape().len()
+        );
+        let m = x.shape()[0];
+        let n = y.shape()[0];
+        let mut a = Matrix::<OwnedMem<T>, DimDyn>::zeros([m, n]);
+        if m == 0 || n == 0 {
+            return a;
+        }
+        // row-majorのa = x y^Tはcol-majorで見るとa^T = y x^Tなので、
+        // col-majorのgerでxとyを入れ替えて計算する
+        M::Blas::ger(
+            BlasLayout::ColMajor,
+            n,
+            m,
+            T::one(),
+            y.as_ptr() as *mut T,
+            y.stride()[0],
+            x.as_ptr() as *mut T,
+            x.stride()[0],
+            a.to_view_mut().as_mut_ptr(),
+            n,
+        );
+        a
+    }
+}
+
+#[cfg(test)]
+mod outer {
+    use crate::{
+        dim::DimTrait,
+        matrix::{MatrixBase, MatrixSliceDyn, OwnedMatrix, ToViewMatrix},
+        matrix_impl::{OwnedMatrix1D, OwnedMatrixDyn},
+        operation::asum::Asum,
+        slice_dynamic,
+    };
+
+    use super::Outer;
+
+    #[test]
+    fn outer_1d() {
+        let x = OwnedMatrix1D::from_vec(vec![1., 2., 3.], [3]);
+        let y = OwnedMatrixDyn::from_vec(vec![1., 10.], [2]);
+        let a = x.outer(&y);
+        assert_eq!(a.shape().slice(), [3, 2]);
+        let ans = OwnedMatrixDyn::from_vec(vec![1., 10., 2., 20., 3., 30.], [3, 2]);
+        assert_eq!((a.to_view() - ans.to_view()).asum(), 0.);
+    }
+
+    #[test]
+    fn outer_strided_view() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        // column 1: [2, 5], row 1 every other element: [4, 6]
+        let col = x.slice_dyn(slice_dynamic!(.., 1));
+        let row = x.slice_dyn(slice_dynamic!(1, ..;2));
+        let a = col.outer(&row);
+        let ans = OwnedMatrixDyn::from_vec(vec![8., 12., 20., 30.], [2, 2]);
+        assert_eq!((a.to_view() - ans.to_view()).asum(), 0.);
+    }
+}
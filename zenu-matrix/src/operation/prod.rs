@@ -0,0 +1,60 @@
+use crate::{
+    dim::DimDyn, matrix_impl::Matrix, matrix_iter::reduce_lanes, memory::ToViewMemory,
+    memory_impl::OwnedMem, num::Num,
+};
+
+pub trait Prod<T: Num> {
+    /// Product of the elements along `axis`, or of every element when `axis` is `None`.
+    fn prod(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Prod<T> for Matrix<M, DimDyn> {
+    fn prod(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        reduce_lanes(self, axis, keep_dim, |lane| {
+            lane.iter().fold(T::one(), |acc, &x| acc * x)
+        })
+    }
+}
+
+#[cfg(test)]
+mod prod {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::transpose::TransposeInplace,
+    };
+
+    use super::Prod;
+
+    #[test]
+    fn prod_all() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [2, 2]);
+        let ans = x.prod(None, false);
+        assert_eq!(ans.shape().slice(), [] as [usize; 0]);
+        assert_eq!(ans.index_item([]), 24.);
+        let ans = x.prod(None, true);
+        assert_eq!(ans.shape().slice(), [1, 1]);
+    }
+
+    #[test]
+    fn prod_axis() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let ans = x.prod(Some(0), false);
+        assert_eq!(ans.shape().slice(), [3]);
+        assert_eq!(ans.index_item([0]), 4.);
+        assert_eq!(ans.index_item([2]), 18.);
+        let ans = x.prod(Some(1), true);
+        assert_eq!(ans.shape().slice(), [2, 1]);
+        assert_eq!(ans.index_item([0, 0]), 6.);
+        assert_eq!(ans.index_item([1, 0]), 120.);
+    }
+
+    #[test]
+    fn prod_view() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let t = x.transepose_by_index(&[1, 0]);
+        let ans = t.prod(Some(1), false);
+        assert_eq!(ans.index_item([1]), 10.);
+    }
+}
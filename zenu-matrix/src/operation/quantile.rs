@@ -0,0 +1,108 @@
+use crate::{
+    dim::DimDyn, matrix_impl::Matrix, matrix_iter::reduce_lanes, memory::ToViewMemory,
+    memory_impl::OwnedMem, num::Num,
+};
+
+pub trait Quantile<T: Num> {
+    /// `q`-th quantile along `axis`, or of every element when `axis` is `None`.
+    ///
+    /// `q` must be in `[0, 1]`. Values between two elements are linearly interpolated,
+    /// like numpy's default `method="linear"`. Lanes that contain NaN give NaN.
+    fn quantile(&self, q: T, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+    /// Same as `quantile(0.5, axis, keep_dim)`.
+    fn median(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Quantile<T> for Matrix<M, DimDyn> {
+    fn quantile(&self, q: T, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        assert!(
+            q >= T::zero() && q <= T::one(),
+            "quantile must be in [0, 1]"
+        );
+        reduce_lanes(self, axis, keep_dim, |lane| quantile_lane(lane, q))
+    }
+
+    fn median(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        self.quantile(T::from(0.5).unwrap(), axis, keep_dim)
+    }
+}
+
+fn quantile_lane<T: Num>(lane: &[T], q: T) -> T {
+    assert!(!lane.is_empty(), "quantile of an empty lane is undefined");
+    if lane.iter().any(|x| x.is_nan()) {
+        return T::nan();
+    }
+    let mut sorted = lane.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let pos = q * T::from_usize(sorted.len() - 1);
+    let lower = pos.floor();
+    let lower_idx = lower.to_usize().unwrap();
+    let upper_idx = pos.ceil().to_usize().unwrap();
+    let frac = pos - lower;
+    sorted[lower_idx] + (sorted[upper_idx] - sorted[lower_idx]) * frac
+}
+
+#[cfg(test)]
+mod quantile {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::transpose::TransposeInplace,
+    };
+
+    use super::Quantile;
+
+    #[test]
+    fn median_odd_even() {
+        let x = OwnedMatrixDyn::from_vec(vec![3., 1., 2.], [3]);
+        assert_eq!(x.median(None, false).index_item([]), 2.);
+        let x = OwnedMatrixDyn::from_vec(vec![4., 1., 3., 2.], [4]);
+        assert_eq!(x.median(None, false).index_item([]), 2.5);
+    }
+
+    #[test]
+    fn quantile_interpolation() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5.], [5]);
+        assert_eq!(x.quantile(0., None, false).index_item([]), 1.);
+        assert_eq!(x.quantile(1., None, false).index_item([]), 5.);
+        assert_eq!(x.quantile(0.3, None, false).index_item([]), 2.2);
+    }
+
+    #[test]
+    fn quantile_axis() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 9., 5., 4., 2., 8.], [2, 3]);
+        let ans = x.median(Some(1), false);
+        assert_eq!(ans.shape().slice(), [2]);
+        assert_eq!(ans.index_item([0]), 5.);
+        assert_eq!(ans.index_item([1]), 4.);
+
+        let ans = x.quantile(0.25, Some(0), true);
+        assert_eq!(ans.shape().slice(), [1, 3]);
+        assert_eq!(ans.index_item([0, 0]), 1.75);
+        assert_eq!(ans.index_item([0, 1]), 3.75);
+    }
+
+    #[test]
+    fn median_view() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 9., 5., 4., 2., 8.], [2, 3]);
+        let t = x.transepose_by_index(&[1, 0]);
+        let ans = t.median(Some(0), false);
+        assert_eq!(ans.index_item([0]), 5.);
+        assert_eq!(ans.index_item([1]), 4.);
+    }
+
+    #[test]
+    fn median_nan() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., f64::NAN, 3.], [3]);
+        assert!(x.median(None, false).index_item([]).is_nan());
+    }
+
+    #[test]
+    #[should_panic]
+    fn quantile_out_of_range() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2.], [2]);
+        x.quantile(1.5, None, false);
+    }
+}
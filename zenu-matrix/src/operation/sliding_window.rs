@@ -0,0 +1,216 @@
+//! Zero-copy sliding window views.
+//!
+//! [`SlidingWindow::windows`] turns one axis into a `(number of windows, window size)`
+//! pair of axes by giving the new window axis the original stride and multiplying the
+//! stride of the original axis by `step`. No element is copied, so windows that overlap
+//! share memory. The views are read-only for that reason.
+//!
+//! Calls can be chained to cut 2-D or 3-D patches, e.g. for pooling or local statistics:
+//!
+//! ```
+//! use zenu_matrix::{
+//!     dim::DimTrait,
+//!     matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+//!     matrix_impl::OwnedMatrixDyn,
+//!     operation::{sliding_window::SlidingWindow, sum::MatrixSum},
+//! };
+//!
+//! let x = OwnedMatrixDyn::from_vec((0..16).map(|x| x as f64).collect(), [4, 4]);
+//! // [4, 4] -> [2, 4, 2] -> [2, 2, 2, 2]
+//! let patches = x.windows(0, 2, 2);
+//! let patches = patches.windows(1, 2, 2);
+//! assert_eq!(patches.shape().slice(), [2, 2, 2, 2]);
+//!
+//! // 2x2 sum pooling
+//! let pooled = patches.sum(3, false);
+//! let pooled = pooled.to_view().sum(2, false);
+//! assert_eq!(pooled.index_item([0, 0]), 0. + 1. + 4. + 5.);
+//! assert_eq!(pooled.index_item([1, 1]), 10. + 11. + 14. + 15.);
+//! ```
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, ToViewMatrix},
+    matrix_impl::Matrix,
+    memory::ToViewMemory,
+    memory_impl::ViewMem,
+    num::Num,
+    shape_stride::ShapeStride,
+};
+
+pub trait SlidingWindow<T: Num> {
+    /// Sliding windows of length `size` taken every `step` elements along `axis`.
+    ///
+    /// `axis` keeps its position and becomes the window index axis of length
+    /// `(shape[axis] - size) / step + 1`; the elements of each window are on a new
+    /// trailing axis of length `size`.
+    fn windows(&self, axis: usize, size: usize, step: usize) -> Matrix<ViewMem<T>, DimDyn>;
+
+    /// View of the same memory with an arbitrary shape and stride.
+    ///
+    /// Strides are counted in elements from the first element of `self`. The new view
+    /// must stay within the elements `self` can reach; otherwise this panics.
+    fn as_strided<I: Into<DimDyn>>(&self, shape: I, stride: I) -> Matrix<ViewMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> SlidingWindow<T> for Matrix<M, DimDyn> {
+    fn windows(&self, axis: usize, size: usize, step: usize) -> Matrix<ViewMem<T>, DimDyn> {
+        let shape = self.shape();
+        let stride = self.stride();
+        assert!(
+            axis < shape.len(),
+            "axis {} is out of range for a {}-D matrix",
+            axis,
+            shape.len()
+        );
+        assert!(size > 0, "window size must be greater than zero");
+        assert!(step > 0, "window step must be greater than zero");
+        assert!(
+            size <= shape[axis],
+            "window size {} is larger than axis {} of length {}",
+            size,
+            axis,
+            shape[axis]
+        );
+        assert!(
+            shape.len() < 6,
+            "windows adds an axis, but the matrix already has the maximum number of axes"
+        );
+
+        let mut new_shape = shape;
+        let mut new_stride = stride;
+        new_shape[axis] = (shape[axis] - size) / step + 1;
+        new_stride[axis] = stride[axis] * step;
+        new_shape.push_dim(size);
+        new_stride.push_dim(stride[axis]);
+
+        let mut view = self.to_view();
+        view.update_shape_stride(ShapeStride::new(new_shape, new_stride));
+        view
+    }
+
+    fn as_strided<I: Into<DimDyn>>(&self, shape: I, stride: I) -> Matrix<ViewMem<T>, DimDyn> {
+        let shape = shape.into();
+        let stride = stride.into();
+        assert_eq!(
+            shape.len(),
+            stride.len(),
+            "shape and stride must have the same number of dimensions"
+        );
+
+        if shape.num_elm() > 0 {
+            let reachable = max_offset(self.shape(), self.stride());
+            let requested = max_offset(shape, stride);
+            assert!(
+                self.shape().num_elm() > 0 && requested <= reachable,
+                "as_strided view reaches offset {}, but the source only reaches offset {}",
+                requested,
+                reachable
+            );
+        }
+
+        let mut view = self.to_view();
+        view.update_shape_stride(ShapeStride::new(shape, stride));
+        view
+    }
+}
+
+/// 最後の要素のoffset
+fn max_offset(shape: DimDyn, stride: DimDyn) -> usize {
+    shape
+        .into_iter()
+        .zip(stride)
+        .map(|(sh, st)| sh.saturating_sub(1) * st)
+        .sum()
+}
+
+#[cfg(test)]
+mod sliding_window {
+    use crate::{
+        dim::DimTrait,
+        matrix::{IndexItem, MatrixBase, OwnedMatrix, ToViewMatrix},
+        matrix_impl::OwnedMatrixDyn,
+        operation::{mean::Mean, transpose::TransposeInplace},
+    };
+
+    use super::SlidingWindow;
+
+    #[test]
+    fn windows_1d() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5.], [5]);
+        let w = x.windows(0, 3, 1);
+        assert_eq!(w.shape().slice(), [3, 3]);
+        assert_eq!(w.stride().slice(), [1, 1]);
+        assert_eq!(w.index_item([0, 0]), 1.);
+        assert_eq!(w.index_item([1, 2]), 4.);
+        assert_eq!(w.index_item([2, 2]), 5.);
+    }
+
+    #[test]
+    fn windows_1d_step() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [6]);
+        let w = x.windows(0, 2, 3);
+        assert_eq!(w.shape().slice(), [2, 2]);
+        assert_eq!(w.index_item([0, 1]), 2.);
+        assert_eq!(w.index_item([1, 0]), 4.);
+        assert_eq!(w.index_item([1, 1]), 5.);
+    }
+
+    #[test]
+    fn windows_2d_local_mean() {
+        // 3x3 moving average
+        let x = OwnedMatrixDyn::from_vec((0..16).map(|x| x as f64).collect(), [4, 4]);
+        let w = x.windows(0, 3, 1);
+        let w = w.windows(1, 3, 1);
+        assert_eq!(w.shape().slice(), [2, 2, 3, 3]);
+        let m = w.mean(Some(3), false);
+        let m = m.to_view().mean(Some(2), false);
+        assert_eq!(m.index_item([0, 0]), 5.);
+        assert_eq!(m.index_item([0, 1]), 6.);
+        assert_eq!(m.index_item([1, 0]), 9.);
+        assert_eq!(m.index_item([1, 1]), 10.);
+    }
+
+    #[test]
+    fn windows_3d_patches() {
+        let x = OwnedMatrixDyn::from_vec((0..27).map(|x| x as f64).collect(), [3, 3, 3]);
+        let w = x.windows(0, 2, 1);
+        let w = w.windows(1, 2, 1);
+        let w = w.windows(2, 2, 1);
+        assert_eq!(w.shape().slice(), [2, 2, 2, 2, 2, 2]);
+        assert_eq!(w.index_item([1, 0, 1, 1, 1, 0]), 22.);
+    }
+
+    #[test]
+    fn windows_on_transposed_view() {
+        let x = OwnedMatrixDyn::from_vec((0..6).map(|x| x as f64).collect(), [2, 3]);
+        let t = x.transepose_by_index(&[1, 0]);
+        let w = t.windows(0, 2, 1);
+        assert_eq!(w.shape().slice(), [2, 2, 2]);
+        // t = [[0, 3], [1, 4], [2, 5]]
+        assert_eq!(w.index_item([1, 1, 0]), 4.);
+        assert_eq!(w.index_item([1, 0, 1]), 2.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn windows_too_large() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3.], [3]);
+        x.windows(0, 4, 1);
+    }
+
+    #[test]
+    fn as_strided_overlapping() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [4]);
+        let y = x.as_strided([3, 2], [1, 1]);
+        assert_eq!(y.index_item([0, 1]), 2.);
+        assert_eq!(y.index_item([2, 0]), 3.);
+        assert_eq!(y.index_item([2, 1]), 4.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn as_strided_out_of_bounds() {
+        let x = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4.], [4]);
+        x.as_strided([3, 2], [2, 1]);
+    }
+}
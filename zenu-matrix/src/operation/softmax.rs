@@ -2,13 +2,15 @@ use crate::{
     dim::{DimDyn, DimTrait},
     matrix::{MatrixBase, ToViewMatrix, ToViewMutMatrix},
     matrix_impl::Matrix,
-    matrix_iter::MatrixIter,
+    matrix_iter::{MatrixElementIter, MatrixIter},
     memory::ToViewMutMemory,
     memory_impl::{ViewMem, ViewMutMem},
     num::Num,
 };
 
-use super::{asum::Asum, copy_from::CopyFrom, exp::Exp, max::MaxIdx};
+use super::{
+    asum::Asum, copy_from::CopyFrom, exp::Exp, logsumexp::logsumexp_kernel_cpu, max::MaxIdx,
+};
 
 pub trait SoftMax<T: Num> {
     fn softmax_assign(&mut self, source: Matrix<ViewMem<T>, DimDyn>, axis: usize);
@@ -29,6 +31,36 @@ impl<T: Num, M: ToViewMutMemory<Item = T>> SoftMax<T> for Matrix<M, DimDyn> {
     }
 }
 
+pub trait LogSoftMax<T: Num> {
+    /// `log(softmax(source))` along `axis`, computed as `x - logsumexp(x)` so that
+    /// confident predictions do not underflow to `-inf`.
+    fn log_softmax_assign(&mut self, source: Matrix<ViewMem<T>, DimDyn>, axis: usize);
+}
+
+impl<T: Num, M: ToViewMutMemory<Item = T>> LogSoftMax<T> for Matrix<M, DimDyn> {
+    fn log_softmax_assign(&mut self, source: Matrix<ViewMem<T>, DimDyn>, axis: usize) {
+        if axis >= self.shape().len() {
+            panic!("axis must be less than the number of dimensions");
+        }
+        self.to_view_mut().copy_from(&source);
+        if self.shape().len() == 1 {
+            log_softmax_kernel_cpu(self.to_view_mut());
+        } else {
+            self.to_view_mut()
+                .map_axis_mut(axis, log_softmax_kernel_cpu);
+        }
+    }
+}
+
+fn log_softmax_kernel_cpu<T: Num>(result: Matrix<ViewMutMem<T>, DimDyn>) {
+    let mut result = result;
+    let lane = result.iter().collect::<Vec<_>>();
+    let lse = logsumexp_kernel_cpu(&lane);
+    for x in result.iter_mut() {
+        *x -= lse;
+    }
+}
+
 fn softmax_kernel_cpu<T: Num>(result: Matrix<ViewMutMem<T>, DimDyn>) {
     let mut result = result;
     let max_diff = result.to_view() - result.to_view().max();
@@ -47,7 +79,7 @@ mod softmax {
         operation::asum::Asum,
     };
 
-    use super::SoftMax;
+    use super::{LogSoftMax, SoftMax};
 
     #[test]
     fn softmax_1d() {
@@ -86,4 +118,35 @@ mod softmax {
         let diff = b.to_view() - ans_2.to_view();
         assert!(diff.asum() < 1e-6);
     }
+
+    #[test]
+    fn log_softmax_2d() {
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let mut b = OwnedMatrixDyn::zeros([2, 3]);
+        b.log_softmax_assign(a.to_view(), 1);
+        let ans = OwnedMatrixDyn::from_vec(
+            vec![
+                -2.40760596,
+                -1.40760596,
+                -0.40760596,
+                -2.40760596,
+                -1.40760596,
+                -0.40760596,
+            ],
+            [2, 3],
+        );
+        let diff = b.to_view() - ans.to_view();
+        assert!(diff.asum() < 1e-6);
+    }
+
+    #[test]
+    fn log_softmax_confident() {
+        // log(softmax(x)) underflows to -inf here
+        let a = OwnedMatrixDyn::from_vec(vec![0., 1000.], [2]);
+        let mut b = OwnedMatrixDyn::zeros([2]);
+        b.log_softmax_assign(a.to_view(), 0);
+        let ans = OwnedMatrixDyn::from_vec(vec![-1000., 0.], [2]);
+        let diff = b.to_view() - ans.to_view();
+        assert!(diff.asum() < 1e-9);
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{
-    dim::DimDyn, matrix::ToViewMatrix, matrix_impl::Matrix, memory_impl::OwnedMem, num::Num,
+    dim::DimDyn, matrix::ToViewMatrix, matrix_impl::Matrix, matrix_iter::MatrixElementIter,
+    memory::ToViewMemory, memory_impl::OwnedMem, num::Num,
 };
 
 use super::mean::Mean;
@@ -8,7 +9,12 @@ pub trait Variance<T: Num> {
     fn variance(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
 }
 
-impl<T: Num> Variance<T> for Matrix<OwnedMem<T>, DimDyn> {
+/// Population standard deviation, the square root of [`Variance::variance`].
+pub trait Std<T: Num> {
+    fn std(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn>;
+}
+
+impl<T: Num, M: ToViewMemory<Item = T>> Variance<T> for Matrix<M, DimDyn> {
     fn variance(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
         let mean = self.mean(axis, true);
         let diff = self.to_view() - mean;
@@ -17,12 +23,23 @@ impl<T: Num> Variance<T> for Matrix<OwnedMem<T>, DimDyn> {
     }
 }
 
+impl<T: Num, M: ToViewMemory<Item = T>> Std<T> for Matrix<M, DimDyn> {
+    fn std(&self, axis: Option<usize>, keep_dim: bool) -> Matrix<OwnedMem<T>, DimDyn> {
+        self.variance(axis, keep_dim).map(|x| x.sqrt())
+    }
+}
+
 #[cfg(test)]
 mod variance {
     use crate::{
-        matrix::OwnedMatrix,
+        dim::DimTrait,
+        matrix::{MatrixBase, OwnedMatrix, ToViewMatrix},
         matrix_impl::OwnedMatrixDyn,
-        operation::{asum::Asum, var::Variance},
+        operation::{
+            asum::Asum,
+            transpose::TransposeInplace,
+            var::{Std, Variance},
+        },
     };
 
     #[test]
@@ -63,4 +80,27 @@ mod variance {
         let ans = x.variance(Some(1), false);
         assert!((ans - 0.25).asum() < 1e-6);
     }
+
+    #[test]
+    fn variance_view() {
+        let x = vec![1.0, 2.0, 3.0, 4.0];
+        let x = OwnedMatrixDyn::from_vec(x, [2, 2]);
+        let t = x.transepose_by_index(&[1, 0]);
+        let ans = t.variance(Some(1), false);
+        assert!((ans - 1.0).asum() < 1e-6);
+        let ans = x.to_view().variance(Some(1), true);
+        assert_eq!(ans.shape().slice(), [2, 1]);
+        assert!((ans - 0.25).asum() < 1e-6);
+    }
+
+    #[test]
+    fn std_2d() {
+        let x = vec![1.0, 3.0, 2.0, 6.0];
+        let x = OwnedMatrixDyn::from_vec(x, [2, 2]);
+        let ans = x.std(Some(1), false);
+        let expected = OwnedMatrixDyn::from_vec(vec![1.0, 2.0], [2]);
+        assert!((ans.to_view() - expected.to_view()).asum() < 1e-6);
+        let ans = x.to_view().std(None, false);
+        assert!((ans - 1.8708286933869707).asum() < 1e-6);
+    }
 }
@@ -0,0 +1,384 @@
+//! Int8 affine quantization.
+//!
+//! A real value `x` is stored as `q = clamp(round(x / scale) + zero_point, -128, 127)` and
+//! restored as `(q - zero_point) * scale`. `scale` and `zero_point` are either shared by
+//! the whole tensor or kept per channel along one axis.
+//!
+//! Quantized matrices multiply with an int8 x int8 -> i32 gemm. The i32 accumulator can be
+//! converted back to `f32` or requantized to int8 with a new scale and zero point.
+//!
+//! ```
+//! use zenu_matrix::{
+//!     matrix::OwnedMatrix,
+//!     matrix_impl::OwnedMatrixDyn,
+//!     quantize::{QuantGranularity, QuantizedMatrix},
+//! };
+//!
+//! let a = OwnedMatrixDyn::from_vec(vec![0.5, -1., 2., 0.25], [2, 2]);
+//! let b = OwnedMatrixDyn::from_vec(vec![1., 0., -0.5, 1.5], [2, 2]);
+//! let qa = QuantizedMatrix::quantize(&a, QuantGranularity::PerChannel(0));
+//! let qb = QuantizedMatrix::quantize(&b, QuantGranularity::PerTensor);
+//! let c = qa.matmul_f32(&qb);
+//! ```
+use crate::{
+    dim::{DimDyn, DimTrait},
+    matrix::{MatrixBase, OwnedMatrix},
+    matrix_impl::Matrix,
+    matrix_iter::MatrixElementIter,
+    memory::ToViewMemory,
+    memory_impl::OwnedMem,
+};
+
+const Q_MIN: i32 = i8::MIN as i32;
+const Q_MAX: i32 = i8::MAX as i32;
+
+/// How `scale` and `zero_point` are shared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantGranularity {
+    /// One `scale` and `zero_point` for the whole tensor.
+    PerTensor,
+    /// One `scale` and `zero_point` for each index of the given axis.
+    PerChannel(usize),
+}
+
+/// An int8 quantized matrix in default stride order.
+#[derive(Clone, Debug)]
+pub struct QuantizedMatrix {
+    data: Vec<i8>,
+    shape: DimDyn,
+    scale: Vec<f32>,
+    zero_point: Vec<i32>,
+    granularity: QuantGranularity,
+}
+
+impl QuantizedMatrix {
+    /// Quantizes `x` with parameters computed from its min and max.
+    ///
+    /// The range always includes zero so that zero is represented exactly.
+    pub fn quantize<M: ToViewMemory<Item = f32>>(
+        x: &Matrix<M, DimDyn>,
+        granularity: QuantGranularity,
+    ) -> Self {
+        let shape = x.shape();
+        let num_channels = num_channels(shape, granularity);
+        let mut min = vec![0f32; num_channels];
+        let mut max = vec![0f32; num_channels];
+        for (i, v) in x.iter().enumerate() {
+            let c = channel_of(shape, granularity, i);
+            min[c] = min[c].min(v);
+            max[c] = max[c].max(v);
+        }
+
+        let mut scale = Vec::with_capacity(num_channels);
+        let mut zero_point = Vec::with_capacity(num_channels);
+        for (min, max) in min.into_iter().zip(max) {
+            let s = (max - min) / (Q_MAX - Q_MIN) as f32;
+            let s = if s > 0. { s } else { 1. };
+            let zp = (Q_MIN as f32 - min / s).round() as i32;
+            scale.push(s);
+            zero_point.push(zp.clamp(Q_MIN, Q_MAX));
+        }
+
+        Self::quantize_with(x, scale, zero_point, granularity)
+    }
+
+    /// Quantizes `x` with the given parameters.
+    ///
+    /// `scale` and `zero_point` have one element for [`QuantGranularity::PerTensor`] and
+    /// one element per channel otherwise.
+    pub fn quantize_with<M: ToViewMemory<Item = f32>>(
+        x: &Matrix<M, DimDyn>,
+        scale: Vec<f32>,
+        zero_point: Vec<i32>,
+        granularity: QuantGranularity,
+    ) -> Self {
+        let shape = x.shape();
+        let num_channels = num_channels(shape, granularity);
+        assert_eq!(scale.len(), num_channels, "wrong number of scales");
+        assert_eq!(
+            zero_point.len(),
+            num_channels,
+            "wrong number of zero points"
+        );
+        assert!(
+            scale.iter().all(|s| *s > 0.),
+            "scale must be greater than zero"
+        );
+
+        let data = x
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let c = channel_of(shape, granularity, i);
+                quantize_value(v, scale[c], zero_point[c])
+            })
+            .collect();
+        Self {
+            data,
+            shape,
+            scale,
+            zero_point,
+            granularity,
+        }
+    }
+
+    pub fn dequantize(&self) -> Matrix<OwnedMem<f32>, DimDyn> {
+        let vec = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, q)| {
+                let c = channel_of(self.shape, self.granularity, i);
+                (*q as i32 - self.zero_point[c]) as f32 * self.scale[c]
+            })
+            .collect();
+        Matrix::from_vec(vec, self.shape)
+    }
+
+    pub fn shape(&self) -> DimDyn {
+        self.shape
+    }
+
+    pub fn data(&self) -> &[i8] {
+        &self.data
+    }
+
+    pub fn scale(&self) -> &[f32] {
+        &self.scale
+    }
+
+    pub fn zero_point(&self) -> &[i32] {
+        &self.zero_point
+    }
+
+    pub fn granularity(&self) -> QuantGranularity {
+        self.granularity
+    }
+
+    /// Int8 gemm `self @ rhs` accumulated in i32, returned row major as `[m, n]`.
+    ///
+    /// `self` must be `[m, k]` quantized per tensor or per row (`PerChannel(0)`), `rhs` must
+    /// be `[k, n]` quantized per tensor or per column (`PerChannel(1)`). Zero points are
+    /// subtracted before multiplying, so element `(i, j)` of the result times
+    /// `self.scale[i] * rhs.scale[j]` is the real valued product.
+    pub fn matmul_i32(&self, rhs: &QuantizedMatrix) -> Vec<i32> {
+        let (m, k, n) = self.gemm_shape(rhs);
+        let mut acc = vec![0i32; m * n];
+        let mut rhs_row = vec![0i32; n];
+        for p in 0..k {
+            for (j, v) in rhs_row.iter_mut().enumerate() {
+                *v = rhs.data[p * n + j] as i32 - rhs.zero_point[rhs.gemm_channel(j)];
+            }
+            for i in 0..m {
+                let a = self.data[i * k + p] as i32 - self.zero_point[self.gemm_channel(i)];
+                if a == 0 {
+                    continue;
+                }
+                let acc_row = &mut acc[i * n..(i + 1) * n];
+                for (c, b) in acc_row.iter_mut().zip(rhs_row.iter()) {
+                    *c += a * b;
+                }
+            }
+        }
+        acc
+    }
+
+    /// Int8 gemm with the i32 result converted back to `f32`.
+    pub fn matmul_f32(&self, rhs: &QuantizedMatrix) -> Matrix<OwnedMem<f32>, DimDyn> {
+        let (m, _, n) = self.gemm_shape(rhs);
+        let acc = self.matmul_i32(rhs);
+        let vec = acc
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| {
+                let scale =
+                    self.scale[self.gemm_channel(idx / n)] * rhs.scale[rhs.gemm_channel(idx % n)];
+                *c as f32 * scale
+            })
+            .collect();
+        Matrix::from_vec(vec, [m, n])
+    }
+
+    /// Int8 gemm requantized to a per tensor int8 output with `out_scale` and
+    /// `out_zero_point`.
+    pub fn matmul_requantize(
+        &self,
+        rhs: &QuantizedMatrix,
+        out_scale: f32,
+        out_zero_point: i32,
+    ) -> QuantizedMatrix {
+        assert!(out_scale > 0., "scale must be greater than zero");
+        let (m, _, n) = self.gemm_shape(rhs);
+        let acc = self.matmul_i32(rhs);
+        let data = acc
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| {
+                // i32のaccumulatorをf32のmultiplierで出力のscaleに合わせる
+                let multiplier = self.scale[self.gemm_channel(idx / n)]
+                    * rhs.scale[rhs.gemm_channel(idx % n)]
+                    / out_scale;
+                let q = (*c as f32 * multiplier).round() as i32 + out_zero_point;
+                q.clamp(Q_MIN, Q_MAX) as i8
+            })
+            .collect();
+        QuantizedMatrix {
+            data,
+            shape: DimDyn::from([m, n]),
+            scale: vec![out_scale],
+            zero_point: vec![out_zero_point],
+            granularity: QuantGranularity::PerTensor,
+        }
+    }
+
+    fn gemm_shape(&self, rhs: &QuantizedMatrix) -> (usize, usize, usize) {
+        assert_eq!(self.shape.len(), 2, "lhs of int8 gemm must be 2-D");
+        assert_eq!(rhs.shape.len(), 2, "rhs of int8 gemm must be 2-D");
+        assert_eq!(
+            self.shape[1], rhs.shape[0],
+            "Dimension mismatch: {:?} @ {:?}",
+            self.shape, rhs.shape
+        );
+        assert!(
+            matches!(
+                self.granularity,
+                QuantGranularity::PerTensor | QuantGranularity::PerChannel(0)
+            ),
+            "lhs of int8 gemm must be quantized per tensor or per row"
+        );
+        assert!(
+            matches!(
+                rhs.granularity,
+                QuantGranularity::PerTensor | QuantGranularity::PerChannel(1)
+            ),
+            "rhs of int8 gemm must be quantized per tensor or per column"
+        );
+        (self.shape[0], self.shape[1], rhs.shape[1])
+    }
+
+    /// gemmの行(lhs)または列(rhs)に対応するchannel
+    fn gemm_channel(&self, index: usize) -> usize {
+        match self.granularity {
+            QuantGranularity::PerTensor => 0,
+            QuantGranularity::PerChannel(_) => index,
+        }
+    }
+}
+
+fn quantize_value(v: f32, scale: f32, zero_point: i32) -> i8 {
+    let q = (v / scale).round() as i32 + zero_point;
+    q.clamp(Q_MIN, Q_MAX) as i8
+}
+
+fn num_channels(shape: DimDyn, granularity: QuantGranularity) -> usize {
+    match granularity {
+        QuantGranularity::PerTensor => 1,
+        QuantGranularity::PerChannel(axis) => {
+            assert!(
+                axis < shape.len(),
+                "axis {} is out of range for a {}-D matrix",
+                axis,
+                shape.len()
+            );
+            shape[axis]
+        }
+    }
+}
+
+/// default strideでのflatなindexが属するchannel
+fn channel_of(shape: DimDyn, granularity: QuantGranularity, index: usize) -> usize {
+    match granularity {
+        QuantGranularity::PerTensor => 0,
+        QuantGranularity::PerChannel(axis) => {
+            let inner: usize = shape.slice()[axis + 1..].iter().product();
+            (index / inner) % shape[axis]
+        }
+    }
+}
+
+#[cfg(test)]
+mod quantize {
+    use crate::{
+        constructor::{rand::normal, zeros::Zeros},
+        dim::DimDyn,
+        matrix::{IndexItem, OwnedMatrix, ToViewMatrix, ToViewMutMatrix},
+        matrix_impl::{Matrix, OwnedMatrixDyn},
+        matrix_iter::MatrixElementIter,
+        memory_impl::OwnedMem,
+        operation::mul::Gemm,
+    };
+
+    use super::{QuantGranularity, QuantizedMatrix};
+
+    fn relative_error(a: &Matrix<OwnedMem<f32>, DimDyn>, b: &Matrix<OwnedMem<f32>, DimDyn>) -> f32 {
+        let diff = a
+            .zip_with(b, |x, y| (x - y) * (x - y))
+            .fold(0., |acc, x| acc + x);
+        let norm = b.fold(0., |acc, x| acc + x * x);
+        (diff / norm).sqrt()
+    }
+
+    #[test]
+    fn per_tensor_roundtrip() {
+        let x = OwnedMatrixDyn::from_vec(vec![-1., -0.5, 0., 0.25, 1., 3.], [2, 3]);
+        let q = QuantizedMatrix::quantize(&x, QuantGranularity::PerTensor);
+        assert_eq!(q.scale().len(), 1);
+        let y = q.dequantize();
+        let scale = q.scale()[0];
+        for (a, b) in x.iter().zip(y.iter()) {
+            assert!((a - b).abs() <= scale / 2. + 1e-6);
+        }
+        // zero is exact
+        assert_eq!(y.index_item([0, 2]), 0.);
+    }
+
+    #[test]
+    fn per_channel_roundtrip() {
+        // channel 1 has a much smaller range than channel 0
+        let x = OwnedMatrixDyn::from_vec(vec![-100., 0.01, 50., -0.02, 100., 0.03], [3, 2]);
+        let q = QuantizedMatrix::quantize(&x, QuantGranularity::PerChannel(1));
+        assert_eq!(q.scale().len(), 2);
+        let y = q.dequantize();
+        for i in 0..3 {
+            let diff = (x.index_item([i, 1]) - y.index_item([i, 1])).abs();
+            assert!(diff <= q.scale()[1] / 2. + 1e-6);
+        }
+        assert!(q.scale()[1] < q.scale()[0]);
+    }
+
+    #[test]
+    fn matmul_i32_exact() {
+        // integer values with scale 1 and zero point 0 multiply exactly
+        let a = OwnedMatrixDyn::from_vec(vec![1., 2., 3., 4., 5., 6.], [2, 3]);
+        let b = OwnedMatrixDyn::from_vec(vec![1., -1., 2., 0., -3., 1.], [3, 2]);
+        let qa = QuantizedMatrix::quantize_with(&a, vec![1.], vec![0], QuantGranularity::PerTensor);
+        let qb = QuantizedMatrix::quantize_with(&b, vec![1.], vec![0], QuantGranularity::PerTensor);
+        // [[1, 2, 3], [4, 5, 6]] @ [[1, -1], [2, 0], [-3, 1]]
+        assert_eq!(qa.matmul_i32(&qb), vec![-4, 2, -4, 2]);
+    }
+
+    #[test]
+    fn matmul_matches_float_gemm() {
+        let a: OwnedMatrixDyn<f32> = normal(0., 1., DimDyn::from([8, 32]), Some(1));
+        let b: OwnedMatrixDyn<f32> = normal(0., 1., DimDyn::from([32, 6]), Some(2));
+        let mut ans = OwnedMatrixDyn::<f32>::zeros([8, 6]);
+        ans.to_view_mut().gemm(a.to_view(), b.to_view());
+
+        for (ga, gb) in [
+            (QuantGranularity::PerTensor, QuantGranularity::PerTensor),
+            (
+                QuantGranularity::PerChannel(0),
+                QuantGranularity::PerChannel(1),
+            ),
+        ] {
+            let qa = QuantizedMatrix::quantize(&a, ga);
+            let qb = QuantizedMatrix::quantize(&b, gb);
+            let c = qa.matmul_f32(&qb);
+            assert!(relative_error(&c, &ans) < 0.02);
+
+            let qc = QuantizedMatrix::quantize(&ans, QuantGranularity::PerTensor);
+            let requantized = qa.matmul_requantize(&qb, qc.scale()[0], qc.zero_point()[0]);
+            assert!(relative_error(&requantized.dequantize(), &ans) < 0.03);
+        }
+    }
+}
@@ -5,8 +5,9 @@ use zenu::{
 };
 use zenu_autograd::{
     creator::from_vec::from_vec,
+    eval_mode,
     functions::{activation::relu::relu, flatten::flatten, loss::cross_entropy::cross_entropy},
-    no_train, set_train, Variable,
+    no_grad, Variable,
 };
 use zenu_layer::{
     layers::{batch_norm::BatchNorm, conv2d::Conv2d, linear::Linear},
@@ -128,16 +129,18 @@ fn main() {
         let mut epoch_loss_val: f32 = 0.;
         let mut num_iter_val = 0;
 
-        no_train();
-        for batch in val_dataloader {
-            let x = batch[0].clone();
-            let y = batch[1].clone();
-            let output = model.predict(&[x]);
-            let loss = cross_entropy(output, y);
-            epoch_loss_val += loss.get_data().index_item([]);
-            num_iter_val += 1;
+        {
+            let _eval = eval_mode();
+            let _no_grad = no_grad();
+            for batch in val_dataloader {
+                let x = batch[0].clone();
+                let y = batch[1].clone();
+                let output = model.predict(&[x]);
+                let loss = cross_entropy(output, y);
+                epoch_loss_val += loss.get_data().index_item([]);
+                num_iter_val += 1;
+            }
         }
-        set_train();
 
         println!(
             "Epoch: {}, Train Loss: {}, Val Loss: {}",
@@ -1,6 +1,6 @@
 use rand::seq::SliceRandom;
 
-use zenu_autograd::{concat::concat, Variable};
+use zenu_autograd::{concat::stack, no_grad, Variable};
 use zenu_matrix::{matrix::MatrixBase, num::Num};
 
 pub fn train_val_split<T: Clone>(data: &[T], split_ratio: f64, shuffle: bool) -> (Vec<T>, Vec<T>) {
@@ -87,7 +87,11 @@ impl<T: Num, D: Dataset<T>> Iterator for DataLoader<T, D> {
             }
         }
 
-        let result: Vec<Variable<T>> = result.iter().map(|v| concat(v)).collect();
+        // バッチはグラフの葉なので、まとめる操作は記録しない
+        let result: Vec<Variable<T>> = {
+            let _guard = no_grad();
+            result.iter().map(|v| stack(v, 0)).collect()
+        };
 
         if result.len() == 1 {
             Some(result)
@@ -12,10 +12,13 @@ pub trait Model<T: Num> {
 }
 
 pub fn update_parameters<T: Num, O: Optimizer<T>>(loss: Variable<T>, optimizer: &O) {
-    loss.backward();
     let parameters = loss.get_all_trainable_variables();
+    // パラメータ以外のグラフはbackwardの途中で解放する
+    loss.backward_with(false, false);
     optimizer.update(&parameters);
-    loss.clear_grad();
+    for parameter in &parameters {
+        parameter.clear_grad();
+    }
 }
 
 pub fn save_model<T: Num, M: Model<T>, P: AsRef<Path>>(